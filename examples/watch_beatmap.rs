@@ -0,0 +1,51 @@
+//! Watches a qualified beatmap on a mock server until it gets ranked.
+//!
+//! ```sh
+//! cargo run --example watch_beatmap
+//! ```
+
+use std::error::Error;
+
+use futures_util::StreamExt;
+use osu_beatmap_watcher::osu::mock::{self, MockServer};
+use osu_beatmap_watcher::osu::types::RankStatus;
+use osu_beatmap_watcher::osu::watcher::DEFAULT_STOP_STATUSES;
+use osu_beatmap_watcher::osu::{OsuApi, WatchEvent};
+use tokio::runtime::Runtime;
+
+const BEATMAP_ID: u32 = 75;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let server = MockServer::start()?;
+    server.set_beatmap(mock::beatmap(BEATMAP_ID, 1, RankStatus::Qualified));
+
+    Runtime::new()?.block_on(async {
+        let api = OsuApi::new(server.url())
+            .log_in("client id", "client secret")
+            .await?;
+        let mut watcher = api.watch(BEATMAP_ID, DEFAULT_STOP_STATUSES.to_vec(), None);
+        while let Some(event) = watcher.next().await {
+            match event {
+                WatchEvent::Beatmap(beatmap) => {
+                    println!(
+                        "{} - {}: {}",
+                        beatmap.beatmapset.artist, beatmap.beatmapset.title, beatmap.ranked
+                    );
+                    // the next poll sees it ranked, ending the stream
+                    server.set_status(BEATMAP_ID, RankStatus::Ranked);
+                }
+                WatchEvent::Transition(transition) => println!("{transition}"),
+                WatchEvent::Offline { retry_in } => {
+                    println!("Offline, retrying in {}s", retry_in.as_secs());
+                }
+                WatchEvent::Error { error, retry_in } => match retry_in {
+                    Some(retry_in) => println!("{error}, retrying in {}s", retry_in.as_secs()),
+                    None => println!("{error}"),
+                },
+                WatchEvent::NotFound => println!("Beatmap not found"),
+            }
+        }
+        println!("{} requests made", server.requests());
+        Ok(())
+    })
+}
@@ -0,0 +1,104 @@
+//! Command line arguments, either options for the GUI or subcommands run
+//! instead of it.
+
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+
+use crate::gui::App;
+use crate::osu::history::{ExportContent, ExportFormat, History};
+
+const USAGE: &str = "\
+Usage:
+  osu-beatmap-watcher [--config FILE]
+  osu-beatmap-watcher export [--format csv|json] [--observations] [--output FILE] [--history FILE] [BEATMAP_ID...]
+
+With --config, settings and the watchlist are read from and saved to the TOML
+FILE. Client secrets and the IRC password are only saved to it if it already
+contains them.
+
+export writes the status history of the given beatmaps, or of all recorded
+beatmaps, to FILE or standard output. With --observations, every recorded
+observation including nomination changes is written instead of the time spent
+in each status. Release builds on Windows have no standard output and require
+--output.";
+
+/// Release builds on Windows are GUI applications without a console.
+const HAS_STDOUT: bool = !cfg!(all(windows, not(debug_assertions)));
+
+#[derive(Default)]
+pub struct GuiOptions {
+    pub config_path: Option<PathBuf>,
+}
+
+/// Runs the subcommand in `args`, returning the GUI options if there is none.
+pub fn run(args: &[String]) -> Result<Option<GuiOptions>, String> {
+    match args.first().map(String::as_str) {
+        Some("export") => export(&args[1..]).map(|()| None),
+        Some("-h" | "--help") => {
+            println!("{USAGE}");
+            Ok(None)
+        }
+        _ => gui_options(args).map(Some),
+    }
+}
+
+fn gui_options(args: &[String]) -> Result<GuiOptions, String> {
+    let mut options = GuiOptions::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => {
+                options.config_path = Some(PathBuf::from(
+                    args.next()
+                        .ok_or_else(|| format!("Missing value for {arg}"))?,
+                ));
+            }
+            _ => return Err(format!("Unknown argument: {arg}\n\n{USAGE}")),
+        }
+    }
+    Ok(options)
+}
+
+fn export(args: &[String]) -> Result<(), String> {
+    let mut format = ExportFormat::Csv;
+    let mut content = ExportContent::StatusPeriods;
+    let mut output = None;
+    let mut history_path = History::default_path(App::NAME);
+    let mut beatmap_ids = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("Missing value for {arg}"))
+        };
+        match arg.as_str() {
+            "--format" => format = value()?.parse()?,
+            "--observations" => content = ExportContent::Observations,
+            "--output" => output = Some(PathBuf::from(value()?)),
+            "--history" => history_path = Some(PathBuf::from(value()?)),
+            _ => beatmap_ids.push(
+                arg.parse::<u32>()
+                    .map_err(|_| format!("Invalid beatmap ID: {arg}\n\n{USAGE}"))?,
+            ),
+        }
+    }
+
+    if output.is_none() && !HAS_STDOUT {
+        return Err(format!("Missing --output\n\n{USAGE}"));
+    }
+    let history_path = history_path.ok_or("No data directory for the history file")?;
+    let history = History::load(&history_path).map_err(|err| err.to_string())?;
+    if beatmap_ids.is_empty() {
+        beatmap_ids = history.beatmap_ids();
+    }
+
+    match output {
+        Some(output) => File::create(output)
+            .and_then(|file| history.export(&beatmap_ids, content, format, file)),
+        None => history.export(&beatmap_ids, content, format, io::stdout().lock()),
+    }
+    .map_err(|err| err.to_string())
+}
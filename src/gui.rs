@@ -1,35 +1,358 @@
-use std::env;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::{env, io, mem};
 
-use eframe::egui::{Color32, ColorImage, Context, Key, TextureFilter, Visuals};
+use eframe::egui::{Color32, ColorImage, Context, Key, Modifiers, TextureFilter, Visuals};
 use eframe::epaint::{Rgba, TextureHandle};
 use eframe::Frame;
+use serde::{Deserialize, Serialize};
 use tokio::task::JoinHandle;
+use tracing::{error, info, warn, Level};
 
-use self::config::Config;
-use crate::osu::client::{Client, LoginState, Update};
-use crate::osu::types::Beatmap;
+use self::alerts::Alerts;
+use self::audio::{Audio, Playback};
+use self::client::{Client, ImportableCollection, LoginState, PollFailure, Update};
+use self::config::{CardLayout, Config};
+use self::palette::{Entry, Palette};
+use self::server::{Page, Server};
+use self::shortcuts::{Action, Bindings};
+use self::tray::{Tray, TrayEvent, TrayWatch};
+use crate::cli::GuiOptions;
+use crate::logging::LogBuffer;
+use crate::osu::difficulty::{self, Mods, PerformanceState};
+use crate::osu::download::DownloadState;
+use crate::osu::feed;
+use crate::osu::history::{self, ExportContent, ExportFormat, History};
+use crate::osu::irc::{IrcConfig, Notifier};
+use crate::osu::link::{self, BeatmapLink};
+use crate::osu::types::{
+    Beatmap, Beatmapset, CoverVariant, GameMode, Hype, NominationsSummary, RankStatus,
+};
+use crate::osu::watcher::Transition;
 
+mod alerts;
+mod audio;
+mod client;
 mod config;
+mod palette;
+mod server;
+mod shortcuts;
+mod tray;
 mod widgets;
 mod windows;
 
-struct State {
-    login_state: LoginState,
+/// eframe storage key of the watches' state between sessions.
+const WATCHES_KEY: &str = "watches";
+/// Delay before calculating star rating and pp again after failing, e.g. to
+/// download the beatmap once back online.
+const PERFORMANCE_RETRY: Duration = Duration::from_secs(30);
+
+struct Watch {
+    beatmap_id: u32,
     worker: Option<JoinHandle<()>>,
-    config_open: bool,
+    /// Statuses the worker stops at, kept from when it was started.
+    stop_statuses: Vec<RankStatus>,
     beatmap: Option<Beatmap>,
+    /// Unix time in seconds of when `beatmap` was polled.
+    last_updated: Option<u64>,
+    /// Whether `beatmap` was polled in this session, not restored from history.
+    fresh: bool,
+    /// Why the last poll failed, `None` if it succeeded.
+    poll_failure: Option<PollFailure>,
     beatmap_cover: Option<TextureHandle>,
+    /// The cover variant and `@2x` form last requested.
+    cover_request: Option<(CoverVariant, bool)>,
+    last_transition: Option<Transition>,
+    download: Option<DownloadState>,
+    preview: Preview,
+    /// Mods to calculate star rating and pp with.
+    mods: Mods,
+    performance: Option<PerformanceState>,
+    /// The mods and beatmap checksum last calculated for.
+    performance_request: Option<(Mods, Option<String>)>,
+    /// When to calculate the same request again after it failed.
+    performance_retry_at: Option<Instant>,
+}
+
+/// A watch as saved on exit, to resume it on the next launch.
+#[derive(Serialize, Deserialize)]
+struct SavedWatch {
+    beatmap_id: u32,
+    running: bool,
+    stop_statuses: Vec<RankStatus>,
+    beatmap: Option<SavedBeatmap>,
+    last_updated: Option<u64>,
+}
+
+/// What the beatmap card shows and updates are compared with, without the
+/// other difficulties of the beatmapset.
+#[derive(Serialize, Deserialize)]
+struct SavedBeatmap {
+    beatmapset_id: u32,
+    artist: String,
+    title: String,
+    creator: String,
+    status: RankStatus,
+    mode: Option<GameMode>,
+    nominations: Option<NominationsSummary>,
+    hype: Option<Hype>,
+    checksum: Option<String>,
+    last_updated: Option<String>,
+    difficulty_rating: f32,
+    ar: f32,
+    accuracy: f32,
+    cs: f32,
+    drain: f32,
+    total_length: u32,
+    count_circles: u32,
+    count_sliders: u32,
+    count_spinners: u32,
+}
+
+impl SavedBeatmap {
+    fn new(beatmap: &Beatmap) -> Self {
+        Self {
+            beatmapset_id: beatmap.beatmapset.id,
+            artist: beatmap.beatmapset.artist.clone(),
+            title: beatmap.beatmapset.title.clone(),
+            creator: beatmap.beatmapset.creator.clone(),
+            status: beatmap.ranked,
+            mode: beatmap.mode,
+            nominations: beatmap.beatmapset.nominations_summary,
+            hype: beatmap.beatmapset.hype,
+            checksum: beatmap.checksum.clone(),
+            last_updated: beatmap.last_updated.clone(),
+            difficulty_rating: beatmap.difficulty_rating,
+            ar: beatmap.ar,
+            accuracy: beatmap.accuracy,
+            cs: beatmap.cs,
+            drain: beatmap.drain,
+            total_length: beatmap.total_length,
+            count_circles: beatmap.count_circles,
+            count_sliders: beatmap.count_sliders,
+            count_spinners: beatmap.count_spinners,
+        }
+    }
+
+    fn into_beatmap(self, beatmap_id: u32) -> Beatmap {
+        Beatmap {
+            id: beatmap_id,
+            ranked: self.status,
+            mode: self.mode,
+            checksum: self.checksum,
+            last_updated: self.last_updated,
+            difficulty_rating: self.difficulty_rating,
+            ar: self.ar,
+            accuracy: self.accuracy,
+            cs: self.cs,
+            drain: self.drain,
+            total_length: self.total_length,
+            count_circles: self.count_circles,
+            count_sliders: self.count_sliders,
+            count_spinners: self.count_spinners,
+            beatmapset: Beatmapset {
+                id: self.beatmapset_id,
+                title: self.title,
+                artist: self.artist,
+                creator: self.creator,
+                nominations_summary: self.nominations,
+                hype: self.hype,
+                beatmaps: Vec::new(),
+            },
+        }
+    }
+}
+
+enum Preview {
+    Stopped,
+    Loading,
+    Playing(Playback),
+}
+
+impl Watch {
+    fn new(beatmap_id: u32) -> Self {
+        Self {
+            beatmap_id,
+            worker: None,
+            stop_statuses: Vec::new(),
+            beatmap: None,
+            last_updated: None,
+            fresh: false,
+            poll_failure: None,
+            beatmap_cover: None,
+            cover_request: None,
+            last_transition: None,
+            download: None,
+            preview: Preview::Stopped,
+            mods: Mods::NONE,
+            performance: None,
+            performance_request: None,
+            performance_retry_at: None,
+        }
+    }
+
+    /// Whether the mode filter lists the watch, always if the mode is unknown.
+    fn is_listed(&self, hidden_modes: &[GameMode]) -> bool {
+        !self
+            .beatmap
+            .as_ref()
+            .and_then(|beatmap| beatmap.mode)
+            .is_some_and(|mode| hidden_modes.contains(&mode))
+    }
+
+    /// Takes the beatmap saved on exit unless the history has a newer one, as
+    /// only the saved one includes nominations. Returns whether the watch was
+    /// running.
+    fn restore(&mut self, saved: SavedWatch) -> bool {
+        if let Some(beatmap) = saved.beatmap {
+            if saved.last_updated >= self.last_updated {
+                self.beatmap = Some(beatmap.into_beatmap(saved.beatmap_id));
+                self.last_updated = saved.last_updated;
+            }
+        }
+        if saved.running {
+            self.stop_statuses = saved.stop_statuses;
+        }
+        saved.running
+    }
+}
+
+struct ExportState {
+    /// Beatmaps to export, all with history when the window opens.
+    selected: Vec<u32>,
+    content: ExportContent,
+    format: ExportFormat,
+    path: String,
+    result: Option<Result<PathBuf, String>>,
+}
+
+impl Default for ExportState {
+    fn default() -> Self {
+        Self {
+            selected: Vec::new(),
+            content: ExportContent::StatusPeriods,
+            format: ExportFormat::Csv,
+            path: String::new(),
+            result: None,
+        }
+    }
+}
+
+/// Importing and exporting the configuration as a file.
+#[derive(Default)]
+struct ConfigFileState {
+    path: String,
+    include_secrets: bool,
+    result: Option<Result<String, String>>,
+}
+
+enum CollectionsState {
+    NotLoaded,
+    Loading,
+    Loaded(Vec<ImportableCollection>),
+    LoadError(String),
+}
+
+#[allow(clippy::struct_field_names, clippy::struct_excessive_bools)]
+struct State {
+    login_state: LoginState,
+    watches: Vec<Watch>,
+    /// The watch keyboard shortcuts act on.
+    selected_watch: Option<u32>,
+    /// The selection changed by keyboard and is not scrolled to yet.
+    scroll_to_selected: bool,
+    palette: Option<Palette>,
+    /// The cover variant fitting the width of banner cards.
+    banner_variant: CoverVariant,
+    /// `config.shortcuts` parsed.
+    bindings: Bindings,
+    /// A link found in the clipboard, offered to be watched.
+    clipboard_link: Option<BeatmapLink>,
+    /// The clipboard text last checked, so a dismissed link is not offered
+    /// again.
+    checked_clipboard: String,
+    /// Whether the window had focus in the previous frame.
+    had_focus: bool,
+    /// Shown below the watch input until dismissed, e.g. why a dropped file
+    /// was rejected.
+    notice: Option<String>,
+    /// Watches to start again once logged in, after switching profiles or
+    /// restarting the app.
+    restart_watches: Vec<u32>,
+    config_open: bool,
+    import_open: bool,
+    export_open: bool,
+    export: ExportState,
+    config_file: ConfigFileState,
+    log_open: bool,
+    log_level: Level,
+    /// The address the server was last started with.
+    served_address: String,
+    server_error: Option<String>,
+    /// The IRC settings connected with, `None` if disabled.
+    irc_config: Option<IrcConfig>,
+    /// Only shows log entries about this beatmap.
+    log_beatmap: Option<u32>,
+    collections: CollectionsState,
+    selected_collection: usize,
+    hide_requested: bool,
+    close_requested: bool,
+    quitting: bool,
     hamster_hack: Option<HamsterHackData>,
 }
 
+impl State {
+    fn watch_mut(&mut self, beatmap_id: u32) -> Option<&mut Watch> {
+        self.watches
+            .iter_mut()
+            .find(|watch| watch.beatmap_id == beatmap_id)
+    }
+
+    fn is_watched(&self, beatmap_id: u32) -> bool {
+        self.watches
+            .iter()
+            .any(|watch| watch.beatmap_id == beatmap_id)
+    }
+
+    fn add_watch(&mut self, beatmap_id: u32) {
+        if !self.is_watched(beatmap_id) {
+            self.watches.push(Watch::new(beatmap_id));
+        }
+    }
+}
+
 impl Default for State {
     fn default() -> Self {
         Self {
             login_state: LoginState::LoggedOut,
-            worker: None,
+            watches: Vec::new(),
+            selected_watch: None,
+            scroll_to_selected: false,
+            palette: None,
+            banner_variant: CoverVariant::Cover,
+            bindings: Bindings::default(),
+            clipboard_link: None,
+            checked_clipboard: String::new(),
+            had_focus: false,
+            notice: None,
+            restart_watches: Vec::new(),
             config_open: false,
-            beatmap: None,
-            beatmap_cover: None,
+            import_open: false,
+            export_open: false,
+            export: ExportState::default(),
+            config_file: ConfigFileState::default(),
+            log_open: false,
+            log_level: Level::INFO,
+            served_address: String::new(),
+            server_error: None,
+            irc_config: None,
+            log_beatmap: None,
+            collections: CollectionsState::NotLoaded,
+            selected_collection: 0,
+            hide_requested: false,
+            close_requested: false,
+            quitting: false,
             hamster_hack: None,
         }
     }
@@ -42,26 +365,76 @@ pub struct HamsterHackData {
 
 pub struct App {
     config: Config,
+    /// Set by `--config`, also saved to besides eframe's storage.
+    config_path: Option<PathBuf>,
     state: State,
     client: Client,
+    tray: Tray,
+    audio: Audio,
+    alerts: Alerts,
+    history: History,
+    history_path: Option<PathBuf>,
+    /// The Atom feed as last written, shared with the server.
+    feed: Arc<Mutex<String>>,
+    feed_path: Option<PathBuf>,
+    /// `.osu` files of watched beatmaps, to calculate pp offline.
+    beatmap_cache: Option<PathBuf>,
+    log: LogBuffer,
+    server: Option<Server>,
+    irc: Option<Notifier>,
     hamster: TextureHandle,
 }
 
 impl App {
     pub const NAME: &'static str = "osu! Beatmap Watcher";
 
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+    pub fn new(cc: &eframe::CreationContext<'_>, options: GuiOptions, log: LogBuffer) -> Self {
         let hamster = image::load_from_memory(include_bytes!(concat!(
             env!("CARGO_MANIFEST_DIR"),
             "/assets/hamster.png"
         )))
         .unwrap();
 
+        let history_path = History::default_path(Self::NAME);
+        let history = history_path
+            .as_deref()
+            .map(History::load)
+            .transpose()
+            .unwrap_or_else(|err| {
+                error!("Loading history failed: {err}");
+                None
+            })
+            .unwrap_or_default();
+
+        let mut config = Config::load_storage(cc.storage.unwrap());
+        if let Some(config_path) = &options.config_path {
+            match Config::load_file(config_path) {
+                Ok(mut file_config) => {
+                    file_config.keep_credentials(&config);
+                    config = file_config;
+                }
+                // created on the next save
+                Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+                Err(err) => error!("Loading {} failed: {err}", config_path.display()),
+            }
+        }
+
         let mut app = Self {
-            config: eframe::get_value::<config::Config>(cc.storage.unwrap(), eframe::APP_KEY)
-                .unwrap_or_default(),
+            config,
+            config_path: options.config_path,
             state: State::default(),
             client: Client::default(),
+            tray: Tray::new(&cc.egui_ctx),
+            audio: Audio::new(),
+            alerts: Alerts::new(),
+            history,
+            history_path,
+            feed: Arc::default(),
+            feed_path: feed::default_path(Self::NAME),
+            beatmap_cache: difficulty::default_cache_dir(Self::NAME),
+            log,
+            server: None,
+            irc: None,
             hamster: cc.egui_ctx.load_texture(
                 "hamster",
                 ColorImage::from_rgba_unmultiplied(
@@ -75,63 +448,750 @@ impl App {
             ),
         };
 
+        if let Some(config_path) = &app.config_path {
+            app.state.config_file.path = config_path.display().to_string();
+        }
+
+        app.state.watches = app
+            .config
+            .watchlist
+            .iter()
+            .copied()
+            .map(Watch::new)
+            .collect();
+        for watch in &mut app.state.watches {
+            if let Some((beatmap, last_seen)) = app.history.last_known(watch.beatmap_id) {
+                watch.beatmap = Some(beatmap);
+                watch.last_updated = Some(last_seen);
+            }
+        }
+        app.restore_watches(
+            eframe::get_value(cc.storage.unwrap(), WATCHES_KEY).unwrap_or_default(),
+        );
+
         cc.egui_ctx.set_visuals(if app.config.dark_mode {
             Visuals::dark()
         } else {
             Visuals::light()
         });
 
-        if !app.config.client_id.is_empty() && !app.config.client_secret.is_empty() {
+        app.state.bindings = Bindings::new(&app.config.shortcuts);
+        app.client.set_rate_limit(app.config.rate_limit);
+        app.update_feed();
+        app.restart_server();
+        app.restart_irc();
+        if app.config.profile().has_credentials() {
             app.state.config_open = false;
-            app.client.log_in(
-                app.config.client_id.clone(),
-                app.config.client_secret.clone(),
-            );
+            app.log_in();
         }
 
         app
     }
 
+    /// Restores the results saved on exit, which unlike the history include
+    /// nominations, and queues the running watches to resume once logged in.
+    fn restore_watches(&mut self, saved_watches: Vec<SavedWatch>) {
+        for saved in saved_watches {
+            let beatmap_id = saved.beatmap_id;
+            if let Some(watch) = self.state.watch_mut(beatmap_id) {
+                if watch.restore(saved) {
+                    self.state.restart_watches.push(beatmap_id);
+                }
+            }
+        }
+    }
+
+    fn saved_watches(&self) -> Vec<SavedWatch> {
+        self.state
+            .watches
+            .iter()
+            .map(|watch| SavedWatch {
+                beatmap_id: watch.beatmap_id,
+                running: watch.worker.is_some()
+                    || self.state.restart_watches.contains(&watch.beatmap_id),
+                stop_statuses: watch.stop_statuses.clone(),
+                beatmap: watch.beatmap.as_ref().map(SavedBeatmap::new),
+                last_updated: watch.last_updated,
+            })
+            .collect()
+    }
+
+    fn log_in(&mut self) {
+        let profile = self.config.profile();
+        self.client.log_in(
+            &profile.api_url,
+            profile.client_id.clone(),
+            profile.client_secret.clone(),
+        );
+    }
+
+    /// Logs in again with the current profile, restarting the running watches
+    /// once logged in.
+    fn log_in_again(&mut self) {
+        for watch in &mut self.state.watches {
+            if let Some(worker) = watch.worker.take() {
+                worker.abort();
+                self.state.restart_watches.push(watch.beatmap_id);
+            }
+        }
+        self.state.login_state = LoginState::LoggedOut;
+        if self.config.profile().has_credentials() {
+            self.log_in();
+        }
+    }
+
+    fn switch_profile(&mut self, profile: usize) {
+        if profile != self.config.profile {
+            self.config.profile = profile;
+            self.log_in_again();
+        }
+    }
+
+    /// Renders the feed from the history and writes it to its file.
+    fn update_feed(&mut self) {
+        let atom = feed::atom(&self.history, Self::NAME, &self.config.profile().api_url);
+        *self.feed.lock().unwrap() = atom;
+        if let Some(feed_path) = &self.feed_path {
+            self.client.write_feed(self.feed.clone(), feed_path.clone());
+        }
+    }
+
+    /// Serves the metrics and the feed on the configured address, if any.
+    fn restart_server(&mut self) {
+        self.server = None;
+        self.state.server_error = None;
+        self.state
+            .served_address
+            .clone_from(&self.config.serve_address);
+        let address = self.config.serve_address.trim();
+        if address.is_empty() {
+            return;
+        }
+
+        let metrics = self.client.metrics();
+        let feed = self.feed.clone();
+        let server = Server::start(address, move |path| match path {
+            "/metrics" => Some(Page {
+                content_type: "text/plain; version=0.0.4",
+                body: metrics.render(),
+            }),
+            "/feed.atom" => Some(Page {
+                content_type: "application/atom+xml",
+                body: feed.lock().unwrap().clone(),
+            }),
+            _ => None,
+        });
+        match server {
+            Ok(server) => {
+                info!("Serving on http://{}", server.address());
+                self.server = Some(server);
+            }
+            Err(err) => {
+                error!("Serving on {address} failed: {err}");
+                self.state.server_error = Some(err.to_string());
+            }
+        }
+    }
+
+    /// Connects to IRC again if the settings changed.
+    fn restart_irc(&mut self) {
+        let irc = &self.config.irc;
+        let config = (self.config.irc_enabled && !irc.nick.is_empty() && !irc.target.is_empty())
+            .then(|| irc.clone());
+        if config != self.state.irc_config {
+            self.irc = config.clone().map(|config| self.client.connect_irc(config));
+            self.state.irc_config = config;
+        }
+    }
+
+    fn remove_watch(&mut self, index: usize) {
+        let watch = self.state.watches.remove(index);
+        if let Some(worker) = watch.worker {
+            worker.abort();
+        }
+        self.client.metrics().remove_beatmap(watch.beatmap_id);
+    }
+
+    fn sync_watchlist(&mut self) {
+        self.config.watchlist = self
+            .state
+            .watches
+            .iter()
+            .map(|watch| watch.beatmap_id)
+            .collect();
+    }
+
+    /// Replaces the configuration, e.g. with one imported from a file, and
+    /// applies what changed.
+    fn apply_config(&mut self, ctx: &Context, mut config: Config) {
+        config.keep_credentials(&self.config);
+        let (previous, profile) = (self.config.profile(), config.profile());
+        let credentials_changed = profile.api_url != previous.api_url
+            || profile.client_id != previous.client_id
+            || profile.client_secret != previous.client_secret;
+        self.config = config;
+        self.state.bindings = Bindings::new(&self.config.shortcuts);
+        self.client.set_rate_limit(self.config.rate_limit);
+
+        for index in (0..self.state.watches.len()).rev() {
+            if !self
+                .config
+                .watchlist
+                .contains(&self.state.watches[index].beatmap_id)
+            {
+                self.remove_watch(index);
+            }
+        }
+        for beatmap_id in self.config.watchlist.clone() {
+            self.state.add_watch(beatmap_id);
+        }
+
+        ctx.set_visuals(if self.config.dark_mode {
+            Visuals::dark()
+        } else {
+            Visuals::light()
+        });
+
+        if self.config.serve_address != self.state.served_address {
+            self.restart_server();
+        }
+        self.restart_irc();
+        if credentials_changed {
+            self.log_in_again();
+        }
+    }
+
     fn process_io(&mut self, ctx: &Context, frame: &mut eframe::Frame) {
-        if let Some(worker) = &self.state.worker {
-            if worker.is_finished() {
-                self.state.worker = None;
+        for watch in &mut self.state.watches {
+            if let Some(worker) = &watch.worker {
+                if worker.is_finished() {
+                    watch.worker = None;
+                }
             }
+            if let Preview::Playing(playback) = &watch.preview {
+                if playback.is_finished() {
+                    watch.preview = Preview::Stopped;
+                }
+            }
+        }
+        let dropped_files = ctx.input().raw.dropped_files.clone();
+        for path in dropped_files.into_iter().filter_map(|file| file.path) {
+            let api = match &self.state.login_state {
+                LoginState::LoggedIn { api } => Some(api),
+                _ => None,
+            };
+            self.client.read_dropped_file(api, path);
         }
-        if ctx.input().key_pressed(Key::Escape) {
+        for event in self.tray.poll_events() {
+            match event {
+                TrayEvent::Show => frame.set_visible(true),
+                TrayEvent::Quit => {
+                    self.state.quitting = true;
+                    frame.close();
+                }
+            }
+        }
+        if self.state.hide_requested {
+            self.state.hide_requested = false;
+            frame.set_visible(false);
+        }
+        let has_focus = ctx.input().raw.has_focus;
+        if has_focus && !self.state.had_focus && self.config.watch_clipboard {
+            self.check_clipboard(false);
+        }
+        self.state.had_focus = has_focus;
+        // before the shortcuts, so Escape closes what is open even if bound
+        let settings_closable =
+            self.state.config_open && matches!(self.state.login_state, LoginState::LoggedIn { .. });
+        let windows_open = self.state.import_open || self.state.export_open || self.state.log_open;
+        if (self.state.palette.is_some() || windows_open || settings_closable)
+            && ctx.input_mut().consume_key(Modifiers::NONE, Key::Escape)
+        {
+            if self.state.palette.is_some() {
+                self.state.palette = None;
+            } else if windows_open {
+                self.state.import_open = false;
+                self.state.export_open = false;
+                self.state.log_open = false;
+            } else {
+                self.state.config_open = false;
+            }
+        }
+        self.process_shortcuts(ctx);
+        if self.state.close_requested {
+            self.state.close_requested = false;
             frame.close();
         }
     }
 
+    fn process_shortcuts(&mut self, ctx: &Context) {
+        if self.modal_open() {
+            return;
+        }
+        let text_focused = ctx.wants_keyboard_input();
+        let triggered = self
+            .state
+            .bindings
+            .iter()
+            .filter(|(action, shortcut)| {
+                // the palette handles keys itself while open
+                !(text_focused && shortcut.is_text_input()
+                    || self.state.palette.is_some() && *action != Action::CommandPalette)
+                    && ctx
+                        .input_mut()
+                        .consume_key(shortcut.modifiers, shortcut.key)
+            })
+            .map(|(action, _)| action)
+            .collect::<Vec<_>>();
+        for action in triggered {
+            self.run_action(ctx, action);
+        }
+    }
+
+    fn run_action(&mut self, ctx: &Context, action: Action) {
+        match action {
+            Action::AddFromClipboard => {
+                if let Some(link) = clipboard_text().as_deref().and_then(link::parse) {
+                    self.watch_link(link);
+                }
+            }
+            Action::ToggleSelectedWatch => {
+                if let Some(beatmap_id) = self.state.selected_watch {
+                    match self
+                        .state
+                        .watch_mut(beatmap_id)
+                        .and_then(|watch| watch.worker.as_ref())
+                    {
+                        Some(worker) => worker.abort(),
+                        None => self.start_watch(beatmap_id),
+                    }
+                }
+            }
+            Action::OpenSettings => self.state.config_open = true,
+            Action::NextWatch => self.select_next_watch(1),
+            Action::PreviousWatch => self.select_next_watch(-1),
+            Action::ToggleTheme => {
+                self.config.dark_mode = !self.config.dark_mode;
+                ctx.set_visuals(if self.config.dark_mode {
+                    Visuals::dark()
+                } else {
+                    Visuals::light()
+                });
+            }
+            Action::CommandPalette => {
+                self.state.palette = match self.state.palette {
+                    Some(_) => None,
+                    None => Some(Palette::default()),
+                };
+            }
+            Action::Close => {
+                // needs the frame, closed in the next `process_io`
+                self.state.close_requested = true;
+                ctx.request_repaint();
+            }
+        }
+    }
+
+    /// Runs an action or selects a watch picked in the palette.
+    fn run_entry(&mut self, ctx: &Context, entry: Entry) {
+        self.state.palette = None;
+        match entry {
+            Entry::Action(action) => self.run_action(ctx, action),
+            Entry::Watch(beatmap_id) => self.select_watch(beatmap_id),
+        }
+    }
+
+    /// Offers to watch a link in the clipboard unless it is watched already.
+    /// Text checked before is only offered again if `force` is set.
+    fn check_clipboard(&mut self, force: bool) {
+        if let Some(text) = clipboard_text() {
+            if force || text != self.state.checked_clipboard {
+                self.state.clipboard_link = link::parse(&text).filter(|link| {
+                    !link
+                        .beatmap_id
+                        .is_some_and(|beatmap_id| self.state.is_watched(beatmap_id))
+                });
+                self.state.checked_clipboard = text;
+            }
+        }
+    }
+
+    /// Watches the beatmap, or all difficulties of a beatmapset, a link points
+    /// to.
+    fn watch_link(&mut self, link: BeatmapLink) {
+        match link {
+            BeatmapLink {
+                beatmap_id: Some(beatmap_id),
+                ..
+            } => {
+                self.state.add_watch(beatmap_id);
+                self.start_watch(beatmap_id);
+                self.select_watch(beatmap_id);
+            }
+            BeatmapLink {
+                beatmapset_id: Some(beatmapset_id),
+                ..
+            } => {
+                if let LoginState::LoggedIn { api } = &self.state.login_state {
+                    self.client.get_beatmapset_beatmaps(api, beatmapset_id);
+                } else {
+                    warn!(beatmapset_id, "Log in to watch all difficulties");
+                }
+            }
+            BeatmapLink { .. } => (),
+        }
+    }
+
+    fn select_watch(&mut self, beatmap_id: u32) {
+        self.state.selected_watch = Some(beatmap_id);
+        self.state.scroll_to_selected = true;
+    }
+
+    /// Moves the selection by `offset` among the listed watches, wrapping
+    /// around.
+    fn select_next_watch(&mut self, offset: isize) {
+        let listed = self
+            .state
+            .watches
+            .iter()
+            .filter(|watch| watch.is_listed(&self.config.hidden_modes))
+            .map(|watch| watch.beatmap_id)
+            .collect::<Vec<_>>();
+        if listed.is_empty() {
+            return;
+        }
+        let index = match self
+            .state
+            .selected_watch
+            .and_then(|selected| listed.iter().position(|id| *id == selected))
+        {
+            Some(index) => {
+                let len = listed.len().cast_signed();
+                (index.cast_signed() + offset)
+                    .rem_euclid(len)
+                    .cast_unsigned()
+            }
+            None if offset < 0 => listed.len() - 1,
+            None => 0,
+        };
+        self.select_watch(listed[index]);
+    }
+
+    fn update_tray(&mut self) {
+        self.tray.set_watches(
+            self.state
+                .watches
+                .iter()
+                .map(|watch| TrayWatch {
+                    title: watch.beatmap.as_ref().map_or_else(
+                        || format!("#{}", watch.beatmap_id),
+                        |beatmap| {
+                            format!(
+                                "{} - {}",
+                                beatmap.beatmapset.artist, beatmap.beatmapset.title
+                            )
+                        },
+                    ),
+                    ranked: watch.beatmap.as_ref().map(|beatmap| beatmap.ranked),
+                })
+                .collect(),
+        );
+    }
+
+    fn start_watch(&mut self, beatmap_id: u32) {
+        if let LoginState::LoggedIn { api } = &self.state.login_state {
+            let api = api.clone();
+            if let Some(watch) = self.state.watch_mut(beatmap_id) {
+                if watch.worker.is_none() {
+                    watch
+                        .stop_statuses
+                        .clone_from(&self.config.profile().stop_statuses);
+                    watch.worker = Some(self.client.poll_beatmap(
+                        &api,
+                        beatmap_id,
+                        watch.stop_statuses.clone(),
+                        watch.beatmap.clone(),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Fetches covers for new beatmaps, or again after the layout or display
+    /// scale changed.
+    fn request_covers(&mut self, ctx: &Context) {
+        let variant = match self.config.card_layout {
+            CardLayout::Compact => CoverVariant::List,
+            CardLayout::Banner => self.state.banner_variant,
+        };
+        let request = (variant, ctx.pixels_per_point() > 1.);
+        for watch in &mut self.state.watches {
+            if let Some(beatmap) = &watch.beatmap {
+                if watch.cover_request != Some(request) {
+                    watch.cover_request = Some(request);
+                    self.client.get_beatmap_cover(
+                        watch.beatmap_id,
+                        beatmap.beatmapset.id,
+                        request.0,
+                        request.1,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Calculates star rating and pp for new beatmaps, again after the mods or
+    /// the beatmap's content changed, or some time after failing.
+    fn request_performance(&mut self) {
+        let now = Instant::now();
+        for watch in &mut self.state.watches {
+            if let Some(beatmap) = &watch.beatmap {
+                let request = (watch.mods, beatmap.checksum.clone());
+                if watch.performance_request.as_ref() != Some(&request)
+                    || watch
+                        .performance_retry_at
+                        .is_some_and(|retry_at| now >= retry_at)
+                {
+                    watch.performance_request = Some(request);
+                    watch.performance_retry_at = None;
+                    watch.performance = Some(PerformanceState::Calculating);
+                    self.client.calculate_performance(
+                        &self.config.profile().api_url,
+                        self.beatmap_cache.clone(),
+                        watch.beatmap_id,
+                        beatmap.checksum.clone(),
+                        watch.mods,
+                    );
+                }
+            }
+        }
+    }
+
+    fn toggle_preview(&mut self, beatmap_id: u32) {
+        let mut beatmapset_id = None;
+        for watch in &mut self.state.watches {
+            if watch.beatmap_id == beatmap_id && matches!(watch.preview, Preview::Stopped) {
+                if let Some(beatmap) = &watch.beatmap {
+                    watch.preview = Preview::Loading;
+                    beatmapset_id = Some(beatmap.beatmapset.id);
+                }
+            } else {
+                // only one preview plays at a time
+                watch.preview = Preview::Stopped;
+            }
+        }
+        if let Some(beatmapset_id) = beatmapset_id {
+            self.client.get_beatmap_preview(beatmap_id, beatmapset_id);
+        }
+    }
+
+    #[allow(clippy::too_many_lines)]
     fn poll_client_updates(&mut self, ctx: &Context) {
+        let mut status_changed = false;
+        // started once the client is not borrowed anymore
+        let mut new_watches = Vec::new();
         for message in self.client.poll_updates() {
             match message {
                 Update::LoginState(state) => {
                     if let LoginState::LoginError(_) = state {
                         self.state.config_open = true;
                     }
+                    if let LoginState::LoggedIn { api } = &state {
+                        for beatmap_id in mem::take(&mut self.state.restart_watches) {
+                            if let Some(watch) = self.state.watch_mut(beatmap_id) {
+                                watch.worker = Some(self.client.poll_beatmap(
+                                    api,
+                                    beatmap_id,
+                                    watch.stop_statuses.clone(),
+                                    watch.beatmap.clone(),
+                                ));
+                            }
+                        }
+                    }
                     self.state.login_state = state;
                 }
-                Update::Beatmap(beatmap) => {
-                    if let Some(new_beatmap) = beatmap.as_ref() {
-                        if self.state.beatmap.is_none() {
-                            self.state.beatmap_cover = None;
-                            self.client.get_beatmap_cover(new_beatmap.id);
+                Update::Beatmap {
+                    beatmap_id,
+                    beatmap,
+                } => {
+                    if let Some(watch) = self.state.watch_mut(beatmap_id) {
+                        watch.poll_failure = None;
+                        if let Some(new_beatmap) = beatmap.as_ref() {
+                            let now = history::now();
+                            self.history.record(new_beatmap, now);
+                            status_changed |= watch
+                                .beatmap
+                                .as_ref()
+                                .is_some_and(|beatmap| beatmap.ranked != new_beatmap.ranked);
+                            watch.last_updated = Some(now);
+                            watch.fresh = true;
+                            if watch.beatmap.is_none() {
+                                watch.beatmap_cover = None;
+                                watch.cover_request = None;
+                                watch.last_transition = None;
+                                watch.download = None;
+                            }
+                            if self.config.auto_download
+                                && !self.config.songs_path.is_empty()
+                                && watch.download.is_none()
+                                && matches!(
+                                    new_beatmap.ranked,
+                                    RankStatus::Ranked | RankStatus::Loved
+                                )
+                            {
+                                watch.download = Some(DownloadState::Downloading {
+                                    downloaded: 0,
+                                    total: None,
+                                });
+                                self.client.download_beatmapset(
+                                    self.config.download_mirror.clone(),
+                                    new_beatmap,
+                                    &self.config.songs_path,
+                                );
+                            }
                         }
+                        watch.beatmap = beatmap;
+                    }
+                }
+                Update::BeatmapCover {
+                    beatmap_id,
+                    variant,
+                    high_dpi,
+                    cover,
+                } => {
+                    if let Some(watch) = self
+                        .state
+                        .watch_mut(beatmap_id)
+                        .filter(|watch| watch.cover_request == Some((variant, high_dpi)))
+                    {
+                        watch.beatmap_cover = Some(ctx.load_texture(
+                            format!("beatmap_cover_{beatmap_id}"),
+                            cover.unwrap_or_else(|| {
+                                ColorImage::new([64, 64], Color32::from_rgb(34, 34, 34))
+                            }),
+                            TextureFilter::Linear,
+                        ));
                     }
-                    self.state.beatmap = beatmap;
-                }
-                Update::BeatmapCover(cover) => {
-                    self.state.beatmap_cover = Some(ctx.load_texture(
-                        "beatmap_cover",
-                        cover.unwrap_or_else(|| {
-                            ColorImage::new([64, 64], Color32::from_rgb(34, 34, 34))
-                        }),
-                        TextureFilter::Linear,
-                    ));
+                }
+                Update::BeatmapPreview {
+                    beatmap_id,
+                    preview,
+                } => {
+                    if let Some(watch) = self.state.watch_mut(beatmap_id) {
+                        if let Preview::Loading = watch.preview {
+                            watch.preview = preview
+                                .and_then(|preview| {
+                                    self.audio
+                                        .play_decoded(&preview, self.config.preview_volume)
+                                })
+                                .map_or(Preview::Stopped, Preview::Playing);
+                        }
+                    }
+                }
+                Update::Performance {
+                    beatmap_id,
+                    mods,
+                    result,
+                } => {
+                    if let Some(watch) = self.state.watch_mut(beatmap_id) {
+                        // results for mods deselected since are dropped
+                        if watch.mods == mods {
+                            watch.performance = Some(match result {
+                                Ok(performance) => PerformanceState::Calculated(performance),
+                                Err(err) => {
+                                    watch.performance_retry_at =
+                                        Some(Instant::now() + PERFORMANCE_RETRY);
+                                    PerformanceState::Failed(err)
+                                }
+                            });
+                        }
+                    }
+                }
+                Update::BeatmapsetBeatmaps {
+                    beatmapset_id,
+                    result,
+                } => match result {
+                    Ok(beatmaps) => {
+                        let (hidden, listed): (Vec<_>, Vec<_>) =
+                            beatmaps.into_iter().partition(|beatmap| {
+                                beatmap
+                                    .mode
+                                    .is_some_and(|mode| self.config.hidden_modes.contains(&mode))
+                            });
+                        info!(
+                            beatmapset_id,
+                            "Watching {} difficulties, skipping {} in hidden modes",
+                            listed.len(),
+                            hidden.len()
+                        );
+                        new_watches.extend(listed.iter().map(|beatmap| beatmap.id));
+                    }
+                    Err(err) => {
+                        self.state.notice = Some(format!("Beatmapset {beatmapset_id}: {err}"));
+                    }
+                },
+                Update::Download { beatmap_id, state } => {
+                    if let Some(watch) = self.state.watch_mut(beatmap_id) {
+                        watch.download = Some(state);
+                    }
+                }
+                Update::Transition {
+                    beatmap_id,
+                    transition,
+                } => {
+                    if let Transition::StatusChanged { to, .. } = transition {
+                        self.alerts.status_changed(&self.audio, &self.config, to);
+                    }
+                    if let Some(watch) = self.state.watch_mut(beatmap_id) {
+                        if let (Some(irc), Some(beatmap), Transition::StatusChanged { .. }) =
+                            (&self.irc, &watch.beatmap, &transition)
+                        {
+                            let beatmapset = &beatmap.beatmapset;
+                            irc.send(format!(
+                                "{} - {} ({}): {transition} {}/beatmaps/{beatmap_id}",
+                                beatmapset.artist,
+                                beatmapset.title,
+                                beatmapset.creator,
+                                self.config.profile().api_url.trim_end_matches('/')
+                            ));
+                        }
+                        watch.last_transition = Some(transition);
+                    }
+                }
+                Update::PollFailed {
+                    beatmap_id,
+                    failure,
+                } => {
+                    if let Some(watch) = self.state.watch_mut(beatmap_id) {
+                        watch.poll_failure = Some(failure);
+                    }
+                }
+                Update::DroppedFile { path, result } => match result {
+                    Ok(beatmap_ids) => new_watches.extend(beatmap_ids),
+                    Err(err) => {
+                        let name = path.file_name().unwrap_or(path.as_os_str());
+                        self.state.notice = Some(format!("{}: {err}", name.to_string_lossy()));
+                    }
+                },
+                Update::Collections(collections) => {
+                    self.state.selected_collection = 0;
+                    self.state.collections = match collections {
+                        Ok(collections) => CollectionsState::Loaded(collections),
+                        Err(err) => CollectionsState::LoadError(err),
+                    };
                 }
             }
         }
+        for &beatmap_id in &new_watches {
+            self.state.add_watch(beatmap_id);
+            self.start_watch(beatmap_id);
+        }
+        if let Some(&beatmap_id) = new_watches.first() {
+            self.select_watch(beatmap_id);
+        }
+        if status_changed {
+            self.update_feed();
+        }
     }
 }
 
@@ -139,11 +1199,38 @@ impl eframe::App for App {
     fn update(&mut self, ctx: &Context, frame: &mut Frame) {
         self.process_io(ctx, frame);
         self.poll_client_updates(ctx);
+        self.request_covers(ctx);
+        self.request_performance();
+        self.update_tray();
+        self.alerts.update(&self.audio, &self.config);
         self.draw(ctx);
+        // keep polling for updates while idle or hidden in the tray
+        ctx.request_repaint_after(Duration::from_secs(1));
+    }
+
+    fn on_close_event(&mut self) -> bool {
+        if self.config.close_to_tray && self.tray.is_available() && !self.state.quitting {
+            self.state.hide_requested = true;
+            false
+        } else {
+            true
+        }
     }
 
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        self.sync_watchlist();
+        if let Some(history_path) = &self.history_path {
+            if let Err(err) = self.history.save(history_path) {
+                error!("Saving history failed: {err}");
+            }
+        }
         eframe::set_value(storage, eframe::APP_KEY, &self.config);
+        eframe::set_value(storage, WATCHES_KEY, &self.saved_watches());
+        if let Some(config_path) = &self.config_path {
+            if let Err(err) = self.config.update_file(config_path) {
+                error!("Saving {} failed: {err}", config_path.display());
+            }
+        }
     }
 
     fn clear_color(&self, _: &Visuals) -> Rgba {
@@ -151,14 +1238,112 @@ impl eframe::App for App {
     }
 
     fn persist_native_window(&self) -> bool {
-        false
+        true
     }
 
     fn persist_egui_memory(&self) -> bool {
-        false
+        true
     }
 
     fn warm_up_enabled(&self) -> bool {
         true
     }
 }
+
+fn clipboard_text() -> Option<String> {
+    match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text()) {
+        Ok(text) => Some(text),
+        Err(err) => {
+            warn!("Reading the clipboard failed: {err}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{SavedBeatmap, SavedWatch, Watch};
+    use crate::osu::history::History;
+    use crate::osu::types::{Beatmap, RankStatus};
+
+    fn qualified_beatmap() -> Beatmap {
+        serde_json::from_value(json!({
+            "id": 75,
+            "ranked": RankStatus::Qualified,
+            "mode": "osu",
+            "checksum": "a",
+            "beatmapset": {
+                "id": 1,
+                "title": "Title",
+                "artist": "Artist",
+                "creator": "Mapper",
+                "nominations_summary": { "current": 2, "required": 2 },
+                "hype": null,
+                "beatmaps": [{ "id": 76, "mode": "osu", "version": "Insane" }],
+            },
+        }))
+        .unwrap()
+    }
+
+    /// A watch as restored from a history last seeing it pending at
+    /// `history_time`.
+    fn watch_with_history(history_time: u64) -> Watch {
+        let mut beatmap = qualified_beatmap();
+        beatmap.ranked = RankStatus::Pending;
+        let mut history = History::default();
+        history.record(&beatmap, history_time);
+
+        let mut watch = Watch::new(75);
+        let (beatmap, last_seen) = history.last_known(75).unwrap();
+        watch.beatmap = Some(beatmap);
+        watch.last_updated = Some(last_seen);
+        watch
+    }
+
+    fn saved_watch(last_updated: u64) -> SavedWatch {
+        SavedWatch {
+            beatmap_id: 75,
+            running: true,
+            stop_statuses: vec![RankStatus::Ranked],
+            beatmap: Some(SavedBeatmap::new(&qualified_beatmap())),
+            last_updated: Some(last_updated),
+        }
+    }
+
+    #[test]
+    fn restores_the_saved_beatmap_unless_the_history_is_newer() {
+        for (history_time, saved_time, expect_saved) in
+            [(100, 200, true), (200, 200, true), (300, 200, false)]
+        {
+            let mut watch = watch_with_history(history_time);
+            assert!(watch.restore(saved_watch(saved_time)));
+
+            let beatmap = watch.beatmap.unwrap();
+            if expect_saved {
+                assert_eq!(beatmap.ranked, RankStatus::Qualified);
+                assert_eq!(beatmap.beatmapset.nominations_summary.unwrap().current, 2);
+                assert_eq!(watch.last_updated, Some(saved_time));
+            } else {
+                assert_eq!(beatmap.ranked, RankStatus::Pending);
+                assert_eq!(watch.last_updated, Some(history_time));
+            }
+            assert_eq!(watch.stop_statuses, [RankStatus::Ranked]);
+        }
+    }
+
+    #[test]
+    fn saves_no_other_difficulties() {
+        let mut watch = Watch::new(75);
+        assert!(!watch.restore(SavedWatch {
+            running: false,
+            ..saved_watch(100)
+        }));
+
+        let beatmap = watch.beatmap.unwrap();
+        assert!(beatmap.beatmapset.beatmaps.is_empty());
+        assert_eq!(beatmap.checksum.as_deref(), Some("a"));
+        assert!(watch.stop_statuses.is_empty());
+    }
+}
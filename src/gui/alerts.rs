@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::audio::{self, Audio, Playback, Sound};
+use super::config::Config;
+use crate::osu::types::RankStatus;
+
+const ALARM_INTERVAL: Duration = Duration::from_secs(3);
+
+struct Alarm {
+    status: RankStatus,
+    started: Instant,
+    playback: Option<Playback>,
+}
+
+pub struct Alerts {
+    playing: Vec<Playback>,
+    alarm: Option<Alarm>,
+    bundled: Option<Sound>,
+    /// User-chosen sounds by path, `None` while loading or if unusable.
+    sounds: HashMap<String, Option<Sound>>,
+    loaded_tx: mpsc::Sender<(String, Option<Sound>)>,
+    loaded_rx: mpsc::Receiver<(String, Option<Sound>)>,
+}
+
+impl Alerts {
+    pub fn new() -> Self {
+        let (loaded_tx, loaded_rx) = mpsc::channel();
+        Self {
+            playing: Vec::new(),
+            alarm: None,
+            bundled: Sound::decode(audio::ALERT),
+            sounds: HashMap::new(),
+            loaded_tx,
+            loaded_rx,
+        }
+    }
+
+    /// Loads newly configured sounds in the background and forgets the ones
+    /// no longer configured. A file is read again only once its path was
+    /// removed from the configuration.
+    fn load(&mut self, config: &Config) {
+        for (path, sound) in self.loaded_rx.try_iter() {
+            if let Some(loaded) = self.sounds.get_mut(&path) {
+                *loaded = sound;
+            }
+        }
+        self.sounds
+            .retain(|path, _| config.alert_sounds.iter().any(|sound| sound.path == *path));
+        for sound in &config.alert_sounds {
+            if !sound.path.is_empty() && !self.sounds.contains_key(&sound.path) {
+                self.sounds.insert(sound.path.clone(), None);
+                let path = sound.path.clone();
+                let loaded_tx = self.loaded_tx.clone();
+                thread::spawn(move || {
+                    let sound = audio::load_sound(&path);
+                    // the receiver only goes away when closing
+                    let _ = loaded_tx.send((path, sound));
+                });
+            }
+        }
+    }
+
+    pub fn alarm_active(&self) -> bool {
+        self.alarm.is_some()
+    }
+
+    /// Plays the bundled sound if the chosen one is still loading or unusable.
+    fn play(&self, audio: &Audio, config: &Config, status: RankStatus) -> Option<Playback> {
+        let alert = config.alert_sound(status)?;
+        let sound = self
+            .sounds
+            .get(&alert.path)
+            .and_then(Option::as_ref)
+            .or(self.bundled.as_ref())?;
+        audio.play_decoded(sound, alert.volume)
+    }
+
+    pub fn status_changed(&mut self, audio: &Audio, config: &Config, status: RankStatus) {
+        if status == RankStatus::Ranked && config.repeat_ranked_alert {
+            self.alarm = Some(Alarm {
+                status,
+                started: Instant::now(),
+                playback: self.play(audio, config, status),
+            });
+        } else if let Some(playback) = self.play(audio, config, status) {
+            self.playing.push(playback);
+        }
+    }
+
+    pub fn test(&mut self, audio: &Audio, config: &Config, status: RankStatus) {
+        if let Some(playback) = self.play(audio, config, status) {
+            self.playing.push(playback);
+        }
+    }
+
+    /// Drops finished sounds, loads changed ones and repeats the alarm until
+    /// it is acknowledged.
+    pub fn update(&mut self, audio: &Audio, config: &Config) {
+        self.playing.retain(|playback| !playback.is_finished());
+        self.load(config);
+
+        let replay = self.alarm.as_ref().and_then(|alarm| {
+            let finished = alarm.playback.as_ref().is_none_or(Playback::is_finished);
+            (finished && alarm.started.elapsed() >= ALARM_INTERVAL).then_some(alarm.status)
+        });
+        if let Some(status) = replay {
+            self.alarm = Some(Alarm {
+                status,
+                started: Instant::now(),
+                playback: self.play(audio, config, status),
+            });
+        }
+    }
+
+    pub fn acknowledge(&mut self) {
+        self.alarm = None;
+    }
+}
@@ -0,0 +1,199 @@
+//! Audio output through the default `sound` feature. Built without it, or
+//! when there is no audio device, nothing is ever played.
+
+use std::fs;
+use std::path::Path;
+#[cfg(feature = "sound")]
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tracing::warn;
+
+pub const ALERT: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/alert.wav"));
+
+/// Reads and decodes a user-chosen sound, `None` if it is unusable. Blocks
+/// for a while with large files.
+pub fn load_sound(path: &str) -> Option<Sound> {
+    match fs::read(Path::new(path)) {
+        Ok(sound) => Sound::decode(sound),
+        Err(err) => {
+            warn!("Reading {path} failed: {err}");
+            None
+        }
+    }
+}
+
+pub struct Audio {
+    #[cfg(feature = "sound")]
+    output: Option<(rodio::OutputStream, rodio::OutputStreamHandle)>,
+}
+
+impl Audio {
+    pub fn new() -> Self {
+        #[cfg(feature = "sound")]
+        {
+            Self {
+                output: rodio::OutputStream::try_default()
+                    .map_err(|err| warn!("No audio output: {err}"))
+                    .ok(),
+            }
+        }
+        #[cfg(not(feature = "sound"))]
+        {
+            Self {}
+        }
+    }
+
+    #[cfg_attr(not(feature = "sound"), allow(clippy::unused_self))]
+    pub fn is_available(&self) -> bool {
+        #[cfg(feature = "sound")]
+        {
+            self.output.is_some()
+        }
+        #[cfg(not(feature = "sound"))]
+        {
+            false
+        }
+    }
+
+    /// Starts playing a sound decoded up front with [`Sound::decode`].
+    #[cfg_attr(not(feature = "sound"), allow(clippy::unused_self))]
+    pub fn play_decoded(&self, sound: &Sound, volume: f32) -> Option<Playback> {
+        #[cfg(feature = "sound")]
+        {
+            let samples = rodio::buffer::SamplesBuffer::new(
+                sound.channels,
+                sound.sample_rate,
+                sound.samples.to_vec(),
+            );
+            self.append(samples, sound.duration, volume)
+        }
+        #[cfg(not(feature = "sound"))]
+        {
+            let _ = (sound, volume);
+            None
+        }
+    }
+
+    #[cfg(feature = "sound")]
+    fn append<S>(&self, source: S, duration: Duration, volume: f32) -> Option<Playback>
+    where
+        S: rodio::Source + Send + 'static,
+        S::Item: rodio::Sample + Send,
+    {
+        let (_, handle) = self.output.as_ref()?;
+        let sink = rodio::Sink::try_new(handle)
+            .map_err(|err| warn!("Playing sound failed: {err}"))
+            .ok()?;
+        sink.set_volume(volume);
+        sink.append(source);
+        Some(Playback {
+            sink,
+            started: Instant::now(),
+            duration,
+        })
+    }
+}
+
+/// A sound decoded ahead of playing, e.g. on a background thread as decoding
+/// a long MP3 takes a while. Cheap to clone and can be played repeatedly.
+#[derive(Clone)]
+pub struct Sound {
+    #[cfg(feature = "sound")]
+    samples: Arc<[i16]>,
+    #[cfg(feature = "sound")]
+    channels: u16,
+    #[cfg(feature = "sound")]
+    sample_rate: u32,
+    #[cfg(feature = "sound")]
+    duration: Duration,
+}
+
+impl Sound {
+    /// Decodes a WAV, OGG or MP3 file, returning `None` if it cannot be
+    /// decoded or sound is not built in.
+    pub fn decode(sound: impl AsRef<[u8]> + Send + Sync + 'static) -> Option<Self> {
+        #[cfg(feature = "sound")]
+        {
+            use rodio::Source;
+
+            let decoder = match rodio::Decoder::new(std::io::Cursor::new(sound)) {
+                Ok(decoder) => decoder,
+                Err(err) => {
+                    warn!("Decoding sound failed: {err}");
+                    return None;
+                }
+            };
+            // decoded in full, as not every format knows its duration
+            let channels = decoder.channels();
+            let sample_rate = decoder.sample_rate();
+            let samples = decoder.collect::<Vec<i16>>();
+            #[allow(clippy::cast_precision_loss)]
+            let duration = Duration::from_secs_f64(
+                samples.len() as f64 / f64::from(channels) / f64::from(sample_rate),
+            );
+            Some(Self {
+                samples: samples.into(),
+                channels,
+                sample_rate,
+                duration,
+            })
+        }
+        #[cfg(not(feature = "sound"))]
+        {
+            drop(sound);
+            None
+        }
+    }
+}
+
+pub struct Playback {
+    #[cfg(feature = "sound")]
+    sink: rodio::Sink,
+    started: Instant,
+    duration: Duration,
+}
+
+impl Playback {
+    #[cfg_attr(not(feature = "sound"), allow(clippy::unused_self))]
+    pub fn is_finished(&self) -> bool {
+        #[cfg(feature = "sound")]
+        {
+            self.sink.empty()
+        }
+        #[cfg(not(feature = "sound"))]
+        {
+            true
+        }
+    }
+
+    pub fn progress(&self) -> f32 {
+        if self.duration.is_zero() {
+            return 1.;
+        }
+        (self.started.elapsed().as_secs_f32() / self.duration.as_secs_f32()).min(1.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load_sound, Audio, Sound, ALERT};
+
+    #[test]
+    fn plays_nothing_without_output() {
+        #[cfg(feature = "sound")]
+        let audio = Audio { output: None };
+        #[cfg(not(feature = "sound"))]
+        let audio = Audio {};
+
+        assert!(!audio.is_available());
+        let sound = Sound::decode(ALERT);
+        assert_eq!(sound.is_some(), cfg!(feature = "sound"));
+        assert!(sound.is_none_or(|sound| audio.play_decoded(&sound, 1.).is_none()));
+    }
+
+    #[test]
+    fn loads_nothing_from_missing_files() {
+        assert!(load_sound("/nonexistent/alert.wav").is_none());
+    }
+}
@@ -0,0 +1,594 @@
+//! Runs API requests in the background for the GUI, which polls their results
+//! every frame.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Instant;
+
+use eframe::epaint::ColorImage;
+use futures_util::StreamExt;
+use image::EncodableLayout;
+use tokio::runtime::Runtime;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use super::audio::Sound;
+use crate::osu::api::{Backoff, OsuApi};
+use crate::osu::beatmap_file::{self, Metadata};
+use crate::osu::db;
+use crate::osu::difficulty::{self, Mods, Performance};
+use crate::osu::download::{self, DownloadState};
+use crate::osu::irc::{IrcConfig, Notifier};
+use crate::osu::metrics::Metrics;
+use crate::osu::rate_limit::RateLimitStats;
+use crate::osu::types::{self, CoverVariant, RankStatus};
+use crate::osu::watcher::{Transition, WatchEvent};
+
+pub enum LoginState {
+    LoggedOut,
+    LoggedIn {
+        api: OsuApi,
+    },
+    LoggingIn,
+    LoginError(String),
+    /// Retrying after a network error.
+    WaitingForNetwork,
+}
+
+pub struct ImportableCollection {
+    pub name: String,
+    pub beatmap_ids: Vec<u32>,
+    /// Beatmaps which are unsubmitted or missing from `osu!.db`.
+    pub missing: usize,
+}
+
+pub struct PollFailure {
+    /// `None` if the API could not be reached.
+    pub error: Option<String>,
+    /// `None` if polling stopped.
+    pub retry_at: Option<Instant>,
+}
+
+pub enum Update {
+    LoginState(LoginState),
+    Beatmap {
+        beatmap_id: u32,
+        beatmap: Option<types::Beatmap>,
+    },
+    BeatmapCover {
+        beatmap_id: u32,
+        variant: CoverVariant,
+        high_dpi: bool,
+        cover: Option<ColorImage>,
+    },
+    BeatmapPreview {
+        beatmap_id: u32,
+        preview: Option<Sound>,
+    },
+    Download {
+        beatmap_id: u32,
+        state: DownloadState,
+    },
+    Transition {
+        beatmap_id: u32,
+        transition: Transition,
+    },
+    /// Polling failed, the beatmap last sent is now stale.
+    PollFailed {
+        beatmap_id: u32,
+        failure: PollFailure,
+    },
+    Performance {
+        beatmap_id: u32,
+        mods: Mods,
+        result: Result<Performance, String>,
+    },
+    /// The difficulties of a beatmapset.
+    BeatmapsetBeatmaps {
+        beatmapset_id: u32,
+        result: Result<Vec<types::BeatmapsetBeatmap>, String>,
+    },
+    Collections(Result<Vec<ImportableCollection>, String>),
+    /// The beatmaps in a dropped file, folder or archive.
+    DroppedFile {
+        path: PathBuf,
+        result: Result<Vec<u32>, String>,
+    },
+}
+
+pub struct Client {
+    /// Not logged in, only used for assets and shared by all logged in clones.
+    api: OsuApi,
+    tx: mpsc::Sender<Update>,
+    rx: mpsc::Receiver<Update>,
+    rt: Runtime,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self {
+            api: OsuApi::default(),
+            tx,
+            rx,
+            rt: Runtime::new().unwrap(),
+        }
+    }
+}
+
+impl Client {
+    /// Logs in to the API at `base_url`, retrying until the network is back.
+    pub fn log_in(&self, base_url: &str, client_id: String, client_secret: String) {
+        self.tx
+            .send(Update::LoginState(LoginState::LoggingIn))
+            .unwrap();
+
+        let api = self.api.with_base_url(base_url);
+        let tx = self.tx.clone();
+
+        self.rt.spawn(async move {
+            let mut backoff = Backoff::new();
+            let logged_in = loop {
+                match api.log_in(&client_id, &client_secret).await {
+                    Ok(api) => {
+                        info!(base_url = api.base_url(), "Logged in");
+                        break LoginState::LoggedIn { api };
+                    }
+                    Err(err) if err.is_network() => {
+                        let retry_in = backoff.next_delay();
+                        warn!(
+                            base_url = api.base_url(),
+                            "Login failed, retrying in {}s: {err}",
+                            retry_in.as_secs()
+                        );
+                        tx.send(Update::LoginState(LoginState::WaitingForNetwork))
+                            .unwrap();
+                        tokio::time::sleep(retry_in).await;
+                    }
+                    Err(err) => {
+                        warn!(base_url = api.base_url(), "Login failed: {err}");
+                        break LoginState::LoginError(
+                            err.status()
+                                .map_or_else(|| err.to_string(), |status| status.to_string()),
+                        );
+                    }
+                }
+            };
+            tx.send(Update::LoginState(logged_in)).unwrap();
+        });
+    }
+
+    /// Forwards the events of a [`crate::osu::Watcher`] as updates, including
+    /// the changes since `previous`.
+    pub fn poll_beatmap(
+        &self,
+        api: &OsuApi,
+        beatmap_id: u32,
+        stop_statuses: Vec<RankStatus>,
+        previous: Option<types::Beatmap>,
+    ) -> JoinHandle<()> {
+        let api = api.clone();
+        let tx = self.tx.clone();
+
+        self.rt.spawn(async move {
+            let mut watcher = api.watch(beatmap_id, stop_statuses, previous);
+            while let Some(event) = watcher.next().await {
+                let update = match event {
+                    WatchEvent::Beatmap(beatmap) => Update::Beatmap {
+                        beatmap_id,
+                        beatmap: Some(beatmap),
+                    },
+                    WatchEvent::Transition(transition) => Update::Transition {
+                        beatmap_id,
+                        transition,
+                    },
+                    WatchEvent::Offline { retry_in } => Update::PollFailed {
+                        beatmap_id,
+                        failure: PollFailure {
+                            error: None,
+                            retry_at: Some(Instant::now() + retry_in),
+                        },
+                    },
+                    WatchEvent::Error { error, retry_in } => Update::PollFailed {
+                        beatmap_id,
+                        failure: PollFailure {
+                            error: Some(error.to_string()),
+                            retry_at: retry_in.map(|retry_in| Instant::now() + retry_in),
+                        },
+                    },
+                    WatchEvent::NotFound => Update::Beatmap {
+                        beatmap_id,
+                        beatmap: None,
+                    },
+                };
+                tx.send(update).unwrap();
+            }
+        })
+    }
+
+    pub fn get_beatmapset_beatmaps(&self, api: &OsuApi, beatmapset_id: u32) {
+        let api = api.clone();
+        let tx = self.tx.clone();
+
+        self.rt.spawn(async move {
+            let result = match api.beatmapset(beatmapset_id).await {
+                Ok(Some(beatmapset)) => Ok(beatmapset.beatmaps),
+                Ok(None) => {
+                    warn!(beatmapset_id, "Beatmapset not found");
+                    Err("Beatmapset not found".to_string())
+                }
+                Err(err) => {
+                    warn!(beatmapset_id, "Fetching beatmapset failed: {err}");
+                    Err(format!("Fetching beatmapset failed: {err}"))
+                }
+            };
+            tx.send(Update::BeatmapsetBeatmaps {
+                beatmapset_id,
+                result,
+            })
+            .unwrap();
+        });
+    }
+
+    pub fn get_beatmap_cover(
+        &self,
+        beatmap_id: u32,
+        beatmapset_id: u32,
+        variant: CoverVariant,
+        high_dpi: bool,
+    ) {
+        self.tx
+            .send(Update::BeatmapCover {
+                beatmap_id,
+                variant,
+                high_dpi,
+                cover: None,
+            })
+            .unwrap();
+
+        let api = self.api.clone();
+        let tx = self.tx.clone();
+
+        self.rt.spawn(async move {
+            let cover = api
+                .beatmap_cover(beatmapset_id, variant, high_dpi)
+                .await
+                .unwrap_or_else(|err| {
+                    warn!(beatmap_id, "Fetching cover failed: {err}");
+                    None
+                })
+                .map(|cover| {
+                    ColorImage::from_rgba_unmultiplied(
+                        [
+                            cover.width().try_into().unwrap(),
+                            cover.height().try_into().unwrap(),
+                        ],
+                        cover.as_bytes(),
+                    )
+                });
+            tx.send(Update::BeatmapCover {
+                beatmap_id,
+                variant,
+                high_dpi,
+                cover,
+            })
+            .unwrap();
+        });
+    }
+
+    pub fn get_beatmap_preview(&self, beatmap_id: u32, beatmapset_id: u32) {
+        let api = self.api.clone();
+        let tx = self.tx.clone();
+
+        self.rt.spawn(async move {
+            let preview = match api.beatmap_preview(beatmapset_id).await {
+                // decoding takes too long for the UI thread
+                Ok(Some(preview)) => tokio::task::spawn_blocking(move || Sound::decode(preview))
+                    .await
+                    .unwrap_or_else(|err| {
+                        warn!(beatmap_id, "Decoding preview failed: {err}");
+                        None
+                    }),
+                Ok(None) => None,
+                Err(err) => {
+                    warn!(beatmap_id, "Fetching preview failed: {err}");
+                    None
+                }
+            };
+            tx.send(Update::BeatmapPreview {
+                beatmap_id,
+                preview,
+            })
+            .unwrap();
+        });
+    }
+
+    /// Calculates on the `.osu` file cached in `cache_dir` if it matches
+    /// `checksum`, downloading it from `base_url` otherwise.
+    pub fn calculate_performance(
+        &self,
+        base_url: &str,
+        cache_dir: Option<PathBuf>,
+        beatmap_id: u32,
+        checksum: Option<String>,
+        mods: Mods,
+    ) {
+        let api = self.api.with_base_url(base_url);
+        let tx = self.tx.clone();
+
+        self.rt.spawn(async move {
+            let result = Self::performance(&api, cache_dir, beatmap_id, checksum, mods)
+                .await
+                .map_err(|err| {
+                    warn!(beatmap_id, "Calculating pp failed: {err}");
+                    err
+                });
+            tx.send(Update::Performance {
+                beatmap_id,
+                mods,
+                result,
+            })
+            .unwrap();
+        });
+    }
+
+    async fn performance(
+        api: &OsuApi,
+        cache_dir: Option<PathBuf>,
+        beatmap_id: u32,
+        checksum: Option<String>,
+        mods: Mods,
+    ) -> Result<Performance, String> {
+        let cached = match &cache_dir {
+            Some(cache_dir) => difficulty::read_cached(cache_dir, beatmap_id, checksum.as_deref()),
+            None => None,
+        };
+        let osu_file = if let Some(osu_file) = cached {
+            osu_file
+        } else {
+            let osu_file = api
+                .beatmap_file(beatmap_id)
+                .await
+                .map_err(|err| err.to_string())?
+                .ok_or_else(|| "No beatmap file".to_string())?;
+            if let Some(cache_dir) = &cache_dir {
+                if let Err(err) = difficulty::write_cached(cache_dir, beatmap_id, &osu_file) {
+                    warn!(beatmap_id, "Caching beatmap failed: {err}");
+                }
+            }
+            osu_file
+        };
+        match tokio::task::spawn_blocking(move || difficulty::calculate(&osu_file, mods)).await {
+            Ok(result) => result.map_err(|err| err.to_string()),
+            Err(err) => Err(format!("Calculating failed: {err}")),
+        }
+    }
+
+    pub fn download_beatmapset(
+        &self,
+        mirror_url: String,
+        beatmap: &types::Beatmap,
+        songs_path: impl Into<PathBuf>,
+    ) {
+        let api = self.api.clone();
+        let tx = self.tx.clone();
+        let beatmap_id = beatmap.id;
+        let beatmapset_id = beatmap.beatmapset.id;
+        let checksum = beatmap.checksum.clone();
+        let songs_path = songs_path.into();
+        let target = songs_path.join(download::folder_name(&beatmap.beatmapset));
+
+        self.rt.spawn(async move {
+            let installed = tokio::task::spawn_blocking(move || {
+                download::installed(&songs_path, beatmapset_id)
+            })
+            .await
+            .unwrap_or_else(|err| Err(io::Error::other(err)));
+            match installed {
+                Ok(Some(path)) => {
+                    info!(beatmap_id, "Already installed in {}", path.display());
+                    tx.send(Update::Download {
+                        beatmap_id,
+                        state: DownloadState::Installed(path),
+                    })
+                    .unwrap();
+                    return;
+                }
+                Ok(None) => (),
+                // downloading anyway, installing will fail if the folder is
+                // unusable
+                Err(err) => warn!(beatmap_id, "Reading the Songs folder failed: {err}"),
+            }
+
+            let progress_tx = tx.clone();
+            let archive = match api
+                .download_beatmapset(&mirror_url, beatmapset_id, |downloaded, total| {
+                    progress_tx
+                        .send(Update::Download {
+                            beatmap_id,
+                            state: DownloadState::Downloading { downloaded, total },
+                        })
+                        .unwrap();
+                })
+                .await
+            {
+                Ok(archive) => archive,
+                Err(err) => {
+                    tx.send(Update::Download {
+                        beatmap_id,
+                        state: DownloadState::Failed(err.to_string()),
+                    })
+                    .unwrap();
+                    error!(beatmap_id, "Download failed: {err}");
+                    return;
+                }
+            };
+
+            tx.send(Update::Download {
+                beatmap_id,
+                state: DownloadState::Installing,
+            })
+            .unwrap();
+            let state = tokio::task::spawn_blocking(move || {
+                match download::install(&archive, checksum.as_deref(), &target) {
+                    Ok(()) => {
+                        info!(beatmap_id, "Installed to {}", target.display());
+                        DownloadState::Installed(target)
+                    }
+                    Err(err) => {
+                        error!(beatmap_id, "Installing failed: {err}");
+                        DownloadState::Failed(err.to_string())
+                    }
+                }
+            })
+            .await
+            .unwrap();
+            tx.send(Update::Download { beatmap_id, state }).unwrap();
+        });
+    }
+
+    pub fn load_collections(&self, osu_path: impl Into<PathBuf>) {
+        let tx = self.tx.clone();
+        let osu_path = osu_path.into();
+
+        self.rt.spawn_blocking(move || {
+            let collections = Self::read_collections(&osu_path).map_err(|err| {
+                warn!("Reading collections failed: {err}");
+                err.to_string()
+            });
+            tx.send(Update::Collections(collections)).unwrap();
+        });
+    }
+
+    /// Writes `feed` to `path` in the background. Writes run one at a time and
+    /// each takes the feed as of when it runs, so the last leaves the newest.
+    pub fn write_feed(&self, feed: Arc<Mutex<String>>, path: PathBuf) {
+        static WRITING: Mutex<()> = Mutex::new(());
+
+        self.rt.spawn_blocking(move || {
+            let _writing = WRITING.lock().unwrap();
+            let atom = feed.lock().unwrap().clone();
+            if let Err(err) = path
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|()| fs::write(&path, atom))
+            {
+                error!("Writing {} failed: {err}", path.display());
+            }
+        });
+    }
+
+    fn read_collections(osu_path: &Path) -> io::Result<Vec<ImportableCollection>> {
+        let collections =
+            db::read_collections(BufReader::new(File::open(osu_path.join("collection.db"))?))?;
+        let beatmaps = db::read_beatmaps(BufReader::new(File::open(osu_path.join("osu!.db"))?))?;
+        let beatmaps = beatmaps
+            .iter()
+            .map(|beatmap| (beatmap.md5.as_str(), beatmap))
+            .collect::<HashMap<_, _>>();
+
+        Ok(collections
+            .into_iter()
+            .map(|collection| {
+                let (beatmap_ids, missing) = db::resolve_collection(&collection, &beatmaps);
+                ImportableCollection {
+                    name: collection.name,
+                    beatmap_ids,
+                    missing,
+                }
+            })
+            .collect())
+    }
+
+    /// Reads a dropped `.osu` file, beatmapset folder or `.osz` archive. Files
+    /// older than v10 only name their beatmapset, those are looked up by
+    /// difficulty name when logged in.
+    pub fn read_dropped_file(&self, api: Option<&OsuApi>, path: PathBuf) {
+        let api = api.cloned();
+        let tx = self.tx.clone();
+
+        self.rt.spawn(async move {
+            let read_path = path.clone();
+            let metadata = tokio::task::spawn_blocking(move || {
+                beatmap_file::read_metadata_from_path(&read_path)
+            })
+            .await
+            .map_err(|err| err.to_string())
+            .and_then(|metadata| metadata.map_err(|err| err.to_string()));
+            let result = match metadata {
+                Ok(metadata) => Self::resolve_dropped_beatmaps(api.as_ref(), &metadata).await,
+                Err(err) => Err(err),
+            };
+            if let Err(err) = &result {
+                warn!("Reading {} failed: {err}", path.display());
+            }
+            tx.send(Update::DroppedFile { path, result }).unwrap();
+        });
+    }
+
+    async fn resolve_dropped_beatmaps(
+        api: Option<&OsuApi>,
+        metadata: &[Metadata],
+    ) -> Result<Vec<u32>, String> {
+        let mut beatmap_ids = Vec::new();
+        let mut beatmapsets = HashMap::<u32, Vec<&str>>::new();
+        for metadata in metadata {
+            match (metadata.beatmap_id, metadata.beatmapset_id) {
+                (Some(beatmap_id), _) => beatmap_ids.push(beatmap_id),
+                (None, Some(beatmapset_id)) => beatmapsets
+                    .entry(beatmapset_id)
+                    .or_default()
+                    .push(&metadata.version),
+                (None, None) => (),
+            }
+        }
+
+        if !beatmapsets.is_empty() {
+            let api = api.ok_or("Log in to watch beatmaps older than file format v10")?;
+            for (beatmapset_id, versions) in beatmapsets {
+                let beatmapset = api
+                    .beatmapset(beatmapset_id)
+                    .await
+                    .map_err(|err| err.to_string())?
+                    .ok_or_else(|| format!("Beatmapset #{beatmapset_id} not found"))?;
+                beatmap_ids.extend(
+                    beatmapset
+                        .beatmaps
+                        .iter()
+                        .filter(|beatmap| versions.contains(&beatmap.version.as_str()))
+                        .map(|beatmap| beatmap.id),
+                );
+            }
+        }
+
+        if beatmap_ids.is_empty() {
+            return Err("No submitted beatmaps found".to_string());
+        }
+        Ok(beatmap_ids)
+    }
+
+    /// Requests per minute shared by all API requests.
+    pub fn set_rate_limit(&self, budget: u32) {
+        self.api.set_rate_limit(budget);
+    }
+
+    pub fn rate_limit_stats(&self) -> RateLimitStats {
+        self.api.rate_limit_stats()
+    }
+
+    pub fn connect_irc(&self, config: IrcConfig) -> Notifier {
+        let _rt = self.rt.enter();
+        Notifier::spawn(config)
+    }
+
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.api.metrics()
+    }
+
+    pub fn poll_updates(&self) -> mpsc::TryIter<'_, Update> {
+        self.rx.try_iter()
+    }
+}
@@ -1,23 +1,386 @@
+use std::path::Path;
+use std::sync::LazyLock;
+use std::{fs, io, mem};
+
 use eframe::emath::Align2;
 use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::shortcuts::Shortcuts;
+use crate::osu::api::DEFAULT_BASE_URL;
+use crate::osu::irc::IrcConfig;
+use crate::osu::rate_limit::{DEFAULT_BUDGET, MAX_BUDGET};
+use crate::osu::types::{GameMode, RankStatus};
+use crate::osu::watcher::DEFAULT_STOP_STATUSES;
 
 #[derive(Serialize, Deserialize)]
-pub struct Config {
+pub struct AlertSound {
+    pub status: RankStatus,
+    pub enabled: bool,
+    /// Empty for the bundled sound.
+    pub path: String,
+    pub volume: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CardLayout {
+    /// A small cover next to the details.
+    Compact,
+    /// The details drawn over a full-width cover.
+    Banner,
+}
+
+/// OAuth client credentials and the API they are used with.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
     pub client_id: String,
     pub client_secret: String,
+    pub api_url: String,
+    /// Watches stop polling once their beatmap has one of these statuses.
+    pub stop_statuses: Vec<RankStatus>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: "Default".to_string(),
+            client_id: String::new(),
+            client_secret: String::new(),
+            api_url: DEFAULT_BASE_URL.to_string(),
+            stop_statuses: DEFAULT_STOP_STATUSES.to_vec(),
+        }
+    }
+}
+
+impl Profile {
+    pub fn has_credentials(&self) -> bool {
+        !self.client_id.is_empty() && !self.client_secret.is_empty()
+    }
+}
+
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub profiles: Vec<Profile>,
+    /// Index of the profile in use.
+    pub profile: usize,
+    /// Credentials saved before there were profiles, see [`Config::migrate`].
+    #[serde(skip_serializing)]
+    client_id: String,
+    #[serde(skip_serializing)]
+    client_secret: String,
+    /// API requests per minute.
+    pub rate_limit: u32,
     pub beatmap_id: String,
+    pub watchlist: Vec<u32>,
     pub dark_mode: bool,
+    pub close_to_tray: bool,
+    /// Offers to watch beatmap links copied to the clipboard when the window
+    /// gets focus.
+    pub watch_clipboard: bool,
     pub hamster_position: Align2,
+    pub card_layout: CardLayout,
+    pub shortcuts: Shortcuts,
+    /// Modes whose watches are hidden from the list.
+    pub hidden_modes: Vec<GameMode>,
+    pub auto_download: bool,
+    pub download_mirror: String,
+    pub songs_path: String,
+    pub osu_path: String,
+    pub alert_sounds: Vec<AlertSound>,
+    pub repeat_ranked_alert: bool,
+    pub preview_volume: f32,
+    /// Local address serving `/metrics` and `/feed.atom`, disabled if empty.
+    pub serve_address: String,
+    /// Announces status changes over IRC.
+    pub irc_enabled: bool,
+    pub irc: IrcConfig,
+}
+
+impl Config {
+    /// Reads a configuration file, leaving fields it does not set at their
+    /// defaults.
+    pub fn load_file(path: &Path) -> io::Result<Self> {
+        let mut config: Self = toml::from_str(&fs::read_to_string(path)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        config.migrate();
+        Ok(config)
+    }
+
+    /// Reads the configuration eframe saved, the default if there is none.
+    pub fn load_storage(storage: &dyn eframe::Storage) -> Self {
+        let mut config = eframe::get_value::<Self>(storage, eframe::APP_KEY).unwrap_or_default();
+        config.migrate();
+        config
+    }
+
+    /// Moves credentials from older configurations into the first profile,
+    /// makes sure a profile is selected and the rate limit is in range.
+    fn migrate(&mut self) {
+        if !(1..=MAX_BUDGET).contains(&self.rate_limit) {
+            warn!(
+                "Rate limit of {} requests per minute is out of range",
+                self.rate_limit
+            );
+            self.rate_limit = self.rate_limit.clamp(1, MAX_BUDGET);
+        }
+        if self.profiles.is_empty() {
+            self.profiles.push(Profile::default());
+        }
+        if !self.client_id.is_empty() && self.profiles[0].client_id.is_empty() {
+            self.profiles[0].client_id = mem::take(&mut self.client_id);
+            self.profiles[0].client_secret = mem::take(&mut self.client_secret);
+        }
+        if self.profile >= self.profiles.len() {
+            self.profile = 0;
+        }
+    }
+
+    /// The profile in use, or the default one if none is, e.g. before
+    /// [`Config::migrate`].
+    pub fn profile(&self) -> &Profile {
+        static DEFAULT: LazyLock<Profile> = LazyLock::new(Profile::default);
+
+        self.profiles
+            .get(self.profile)
+            .or_else(|| self.profiles.first())
+            .unwrap_or(&DEFAULT)
+    }
+
+    /// Only valid after [`Config::migrate`], which loading runs.
+    pub fn profile_mut(&mut self) -> &mut Profile {
+        &mut self.profiles[self.profile]
+    }
+
+    /// Writes the configuration as TOML, leaving out client secrets and the IRC
+    /// password unless `include_secrets` is set.
+    pub fn save_file(&self, path: &Path, include_secrets: bool) -> io::Result<()> {
+        let mut config = self.to_toml()?;
+        if !include_secrets {
+            Self::remove_secrets(&mut config, None);
+        }
+        fs::write(path, config.to_string())
+    }
+
+    /// Writes back to a file the configuration was loaded from, keeping the
+    /// secrets the file contains but adding none.
+    pub fn update_file(&self, path: &Path) -> io::Result<()> {
+        let existing = match fs::read_to_string(path) {
+            Ok(existing) => toml::from_str::<toml::Value>(&existing).ok(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err),
+        };
+        let mut config = self.to_toml()?;
+        Self::remove_secrets(&mut config, existing.as_ref());
+        fs::write(path, config.to_string())
+    }
+
+    fn to_toml(&self) -> io::Result<toml::Value> {
+        let mut config = toml::Value::try_from(self)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        if let Some(config) = config.as_table_mut() {
+            // only typed into the main window
+            config.remove("beatmap_id");
+        }
+        Ok(config)
+    }
+
+    /// Removes client secrets and the IRC password, except those also in
+    /// `kept`, for the profile of the same name.
+    fn remove_secrets(config: &mut toml::Value, kept: Option<&toml::Value>) {
+        let kept_profiles = kept
+            .and_then(|kept| kept.get("profiles"))
+            .and_then(toml::Value::as_array)
+            .map_or(&[][..], Vec::as_slice);
+        let profiles = config
+            .get_mut("profiles")
+            .and_then(toml::Value::as_array_mut)
+            .into_iter()
+            .flatten()
+            .filter_map(toml::Value::as_table_mut);
+        for profile in profiles {
+            let keep = kept_profiles.iter().any(|kept| {
+                kept.get("name") == profile.get("name") && kept.get("client_secret").is_some()
+            });
+            if !keep {
+                profile.remove("client_secret");
+            }
+        }
+
+        let keep_password = kept
+            .and_then(|kept| kept.get("irc"))
+            .and_then(|irc| irc.get("password"))
+            .is_some();
+        if let Some(irc) = config.get_mut("irc").and_then(toml::Value::as_table_mut) {
+            if !keep_password {
+                irc.remove("password");
+            }
+        }
+    }
+
+    /// Keeps the credentials of profiles in `previous` with the same name, and
+    /// the IRC password for the same nick, if this configuration has none, e.g.
+    /// as it was loaded from a file without secrets.
+    pub fn keep_credentials(&mut self, previous: &Config) {
+        if self.irc.password.is_empty() && self.irc.nick == previous.irc.nick {
+            self.irc.password.clone_from(&previous.irc.password);
+        }
+        for profile in &mut self.profiles {
+            if let Some(previous) = previous
+                .profiles
+                .iter()
+                .find(|previous| previous.name == profile.name)
+            {
+                if profile.client_id.is_empty() {
+                    profile.client_id.clone_from(&previous.client_id);
+                }
+                if profile.client_secret.is_empty() && profile.client_id == previous.client_id {
+                    profile.client_secret.clone_from(&previous.client_secret);
+                }
+            }
+        }
+    }
+
+    pub fn alert_sound(&self, status: RankStatus) -> Option<&AlertSound> {
+        self.alert_sounds
+            .iter()
+            .find(|sound| sound.status == status && sound.enabled)
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            profiles: vec![Profile::default()],
+            profile: 0,
             client_id: String::new(),
             client_secret: String::new(),
+            rate_limit: DEFAULT_BUDGET,
             beatmap_id: String::new(),
+            watchlist: Vec::new(),
             dark_mode: true,
+            close_to_tray: false,
+            watch_clipboard: false,
             hamster_position: Align2::RIGHT_BOTTOM,
+            card_layout: CardLayout::Compact,
+            shortcuts: Shortcuts::default(),
+            hidden_modes: Vec::new(),
+            auto_download: false,
+            download_mirror: "https://api.nerinyan.moe/d".to_string(),
+            songs_path: String::new(),
+            osu_path: String::new(),
+            alert_sounds: RankStatus::ALL
+                .into_iter()
+                .map(|status| AlertSound {
+                    status,
+                    enabled: matches!(
+                        status,
+                        RankStatus::Ranked
+                            | RankStatus::Approved
+                            | RankStatus::Qualified
+                            | RankStatus::Loved
+                    ),
+                    path: String::new(),
+                    volume: 0.5,
+                })
+                .collect(),
+            repeat_ranked_alert: false,
+            preview_volume: 0.5,
+            serve_address: String::new(),
+            irc_enabled: false,
+            irc: IrcConfig::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::{env, fs, process};
+
+    use super::{Config, Profile};
+
+    #[derive(Default)]
+    struct MemoryStorage(HashMap<String, String>);
+
+    impl eframe::Storage for MemoryStorage {
+        fn get_string(&self, key: &str) -> Option<String> {
+            self.0.get(key).cloned()
+        }
+
+        fn set_string(&mut self, key: &str, value: String) {
+            self.0.insert(key.to_string(), value);
         }
+
+        fn flush(&mut self) {}
+    }
+
+    #[test]
+    fn migrates_credentials_from_storage() {
+        let mut storage = MemoryStorage::default();
+        eframe::Storage::set_string(
+            &mut storage,
+            eframe::APP_KEY,
+            r#"(client_id: "1", client_secret: "secret", watchlist: [42])"#.to_string(),
+        );
+
+        let config = Config::load_storage(&storage);
+        assert_eq!(config.profiles.len(), 1);
+        assert_eq!(config.profile().client_id, "1");
+        assert_eq!(config.profile().client_secret, "secret");
+        assert_eq!(config.watchlist, [42]);
+
+        // kept once saved again
+        eframe::set_value(&mut storage, eframe::APP_KEY, &config);
+        assert_eq!(
+            Config::load_storage(&storage).profile().client_secret,
+            "secret"
+        );
+    }
+
+    #[test]
+    fn profile_without_profiles() {
+        let mut config = Config {
+            profiles: Vec::new(),
+            profile: 3,
+            ..Config::default()
+        };
+        assert_eq!(config.profile().name, "Default");
+        config.migrate();
+        config.profile_mut().client_id = "1".to_string();
+        assert_eq!(config.profile, 0);
+        assert_eq!(config.profiles[0].client_id, "1");
+    }
+
+    #[test]
+    fn update_file_keeps_secrets_the_file_had() {
+        let path = env::temp_dir().join(format!("osu-beatmap-watcher-{}.toml", process::id()));
+        let mut config = Config::default();
+        config.profiles[0].client_id = "1".to_string();
+        config.profiles[0].client_secret = "secret".to_string();
+        config.profiles.push(Profile {
+            name: "Other".to_string(),
+            client_id: "2".to_string(),
+            client_secret: "other secret".to_string(),
+            ..Profile::default()
+        });
+        config.irc.password = "password".to_string();
+        config.save_file(&path, true).unwrap();
+
+        // the secrets removed from the file by hand stay out
+        let mut file = fs::read_to_string(&path).unwrap();
+        file = file.replace("client_secret = \"other secret\"", "");
+        fs::write(&path, file).unwrap();
+        config.update_file(&path).unwrap();
+        let updated = Config::load_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(updated.profiles[0].client_secret, "secret");
+        assert_eq!(updated.profiles[1].client_id, "2");
+        assert_eq!(updated.profiles[1].client_secret, "");
+        assert_eq!(updated.irc.password, "password");
     }
 }
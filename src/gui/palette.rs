@@ -0,0 +1,111 @@
+//! The command palette, searching actions and watched beatmaps by name.
+
+use super::shortcuts::Action;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Entry {
+    Action(Action),
+    Watch(u32),
+}
+
+#[derive(Default)]
+pub struct Palette {
+    pub query: String,
+    /// Index into the entries matching `query`.
+    pub selected: usize,
+    /// The selection moved since it was last scrolled to.
+    pub scroll_to_selected: bool,
+}
+
+impl Palette {
+    /// `entries` with their labels, best match first.
+    pub fn matches(&self, entries: Vec<(Entry, String)>) -> Vec<(Entry, String)> {
+        let mut matches = entries
+            .into_iter()
+            .filter_map(|(entry, label)| {
+                fuzzy_score(&self.query, &label).map(|score| (score, entry, label))
+            })
+            .collect::<Vec<_>>();
+        // stable, so equally good matches keep their order
+        matches.sort_by_key(|(score, ..)| std::cmp::Reverse(*score));
+        matches
+            .into_iter()
+            .map(|(_, entry, label)| (entry, label))
+            .collect()
+    }
+}
+
+/// How well the characters of `query` appear in order in `text`, ignoring case
+/// and spaces. Higher is better, `None` if they do not all appear.
+fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text = text
+        .chars()
+        .flat_map(char::to_lowercase)
+        .collect::<Vec<_>>();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous = None;
+    for query_char in query
+        .chars()
+        .filter(|char| !char.is_whitespace())
+        .flat_map(char::to_lowercase)
+    {
+        let found = position
+            + text[position..]
+                .iter()
+                .position(|char| *char == query_char)?;
+        // runs of matching characters and word starts count most
+        score += if previous.is_some_and(|previous| previous + 1 == found) {
+            3
+        } else if found == 0 || !text[found - 1].is_alphanumeric() {
+            2
+        } else {
+            1
+        };
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_characters_in_order() {
+        assert!(fuzzy_score("stg", "Open settings").is_some());
+        assert!(fuzzy_score("OPEN set", "open settings").is_some());
+        assert_eq!(fuzzy_score("", "Open settings"), Some(0));
+        assert_eq!(fuzzy_score("gts", "Open settings"), None);
+        assert_eq!(fuzzy_score("openx", "Open settings"), None);
+    }
+
+    #[test]
+    fn prefers_runs_and_word_starts() {
+        // a run after a word start, word starts, then scattered characters
+        assert_eq!(fuzzy_score("set", "Open settings"), Some(2 + 3 + 3));
+        assert_eq!(fuzzy_score("os", "Open settings"), Some(2 + 2));
+        assert_eq!(fuzzy_score("pe", "Open settings"), Some(1 + 3));
+    }
+
+    #[test]
+    fn sorts_best_match_first_and_keeps_ties_in_order() {
+        let palette = Palette {
+            query: "wa".to_string(),
+            ..Palette::default()
+        };
+        let entries = vec![
+            (Entry::Watch(1), "#1 Nekomata - Swan".to_string()),
+            (Entry::Watch(2), "#2 Camellia - Wave".to_string()),
+            (Entry::Watch(3), "#3 xi - Blue".to_string()),
+            (Entry::Watch(4), "#4 Kano - Walk".to_string()),
+        ];
+        let matches = palette
+            .matches(entries)
+            .into_iter()
+            .map(|(entry, _)| entry)
+            .collect::<Vec<_>>();
+        assert_eq!(matches, [Entry::Watch(2), Entry::Watch(4), Entry::Watch(1)]);
+    }
+}
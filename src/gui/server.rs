@@ -0,0 +1,256 @@
+//! A local HTTP endpoint answering GET requests, e.g. scrapes by Prometheus.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use tracing::{debug, warn};
+
+/// How long a client may take for each read and write.
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// Requests answered at once, connections beyond are closed right away.
+const MAX_CONNECTIONS: usize = 8;
+/// Longest request line and headers read, the rest is ignored.
+const MAX_REQUEST_SIZE: u64 = 16 * 1024;
+
+pub struct Page {
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+/// Serves until dropped.
+pub struct Server {
+    address: SocketAddr,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Server {
+    /// Answers each request with the page `handler` returns for its path, or
+    /// 404 if there is none.
+    pub fn start(
+        address: &str,
+        handler: impl Fn(&str) -> Option<Page> + Send + Sync + 'static,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let handler = Arc::new(handler);
+        let connections = Arc::new(AtomicUsize::new(0));
+
+        let thread_shutdown = shutdown.clone();
+        let thread = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if thread_shutdown.load(Ordering::Relaxed) {
+                    break;
+                }
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        warn!("Accepting connection failed: {err}");
+                        continue;
+                    }
+                };
+                let connection = Connection::open(&connections);
+                if connections.load(Ordering::SeqCst) > MAX_CONNECTIONS {
+                    debug!("Too many connections, closing one");
+                    continue;
+                }
+                // answered on their own thread, so a slow client delays no
+                // other requests and the listener stops right away
+                let handler = handler.clone();
+                thread::spawn(move || {
+                    if let Err(err) = handle(stream, &*handler) {
+                        warn!("Serving request failed: {err}");
+                    }
+                    drop(connection);
+                });
+            }
+        });
+
+        Ok(Self {
+            address,
+            shutdown,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        // wakes up the listener to see the shutdown
+        let mut address = self.address;
+        if address.ip().is_unspecified() {
+            address.set_ip(match address.ip() {
+                IpAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+                IpAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+            });
+        }
+        TcpStream::connect(address).ok();
+        // frees the address before it may be bound again, without waiting for
+        // requests being answered
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+/// Counts towards [`MAX_CONNECTIONS`] until dropped.
+struct Connection(Arc<AtomicUsize>);
+
+impl Connection {
+    fn open(connections: &Arc<AtomicUsize>) -> Self {
+        connections.fetch_add(1, Ordering::SeqCst);
+        Self(connections.clone())
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn handle(mut stream: TcpStream, handler: &impl Fn(&str) -> Option<Page>) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let request_line = read_request(stream.try_clone()?)?;
+    let mut request_line = request_line.split_whitespace();
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line
+        .next()
+        .unwrap_or_default()
+        .split('?')
+        .next()
+        .unwrap_or_default();
+    debug!("{method} {path}");
+
+    let (status, page) = match handler(path) {
+        Some(page) if method == "GET" => ("200 OK", page),
+        Some(_) => ("405 Method Not Allowed", empty_page()),
+        None => ("404 Not Found", empty_page()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        page.content_type,
+        page.body.len(),
+        page.body
+    )?;
+    stream.flush()
+}
+
+/// Reads up to [`MAX_REQUEST_SIZE`] of the request, returning its first line.
+fn read_request(stream: impl Read) -> io::Result<String> {
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST_SIZE));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        if header.trim().is_empty() {
+            break;
+        }
+    }
+    Ok(request_line)
+}
+
+fn empty_page() -> Page {
+    Page {
+        content_type: "text/plain",
+        body: String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::time::{Duration, Instant};
+
+    use super::{read_request, Page, Server, MAX_CONNECTIONS, MAX_REQUEST_SIZE};
+
+    fn get(server: &Server, request: &str) -> String {
+        let mut stream = TcpStream::connect(server.address()).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn start() -> Server {
+        Server::start("127.0.0.1:0", |path| {
+            (path == "/metrics").then(|| Page {
+                content_type: "text/plain",
+                body: "up 1".to_string(),
+            })
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn answers_while_another_client_is_slow() {
+        let server = start();
+        let _slow = TcpStream::connect(server.address()).unwrap();
+
+        let start = Instant::now();
+        let response = get(&server, "GET /metrics HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\nup 1"));
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        let start = Instant::now();
+        drop(server);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn closes_connections_beyond_the_limit() {
+        let server = start();
+        let mut idle = (0..MAX_CONNECTIONS)
+            .map(|_| TcpStream::connect(server.address()).unwrap())
+            .collect::<Vec<_>>();
+
+        // closed without an answer, possibly reset as the request is unread
+        let mut stream = TcpStream::connect(server.address()).unwrap();
+        stream.write_all(b"GET /metrics HTTP/1.1\r\n\r\n").ok();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).ok();
+        assert!(response.is_empty());
+
+        // answered again once a connection is done
+        idle.pop();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        loop {
+            let mut stream = TcpStream::connect(server.address()).unwrap();
+            stream.write_all(b"GET /metrics HTTP/1.1\r\n\r\n").ok();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).ok();
+            if response.starts_with("HTTP/1.1 200 OK\r\n") {
+                break;
+            }
+            assert!(Instant::now() < deadline, "no connection freed");
+        }
+    }
+
+    #[test]
+    fn reads_requests_up_to_the_limit() {
+        let request = "GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        assert_eq!(
+            read_request(request.as_bytes()).unwrap(),
+            "GET /metrics HTTP/1.1\r\n"
+        );
+
+        let request = format!("GET /{}", "a".repeat(64 * 1024));
+        let request_line = read_request(request.as_bytes()).unwrap();
+        assert_eq!(request_line.len() as u64, MAX_REQUEST_SIZE);
+    }
+}
@@ -0,0 +1,345 @@
+//! Keyboard shortcuts, written like `Ctrl+Shift+V` in the configuration.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use eframe::egui::{Key, Modifiers};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    AddFromClipboard,
+    ToggleSelectedWatch,
+    OpenSettings,
+    NextWatch,
+    PreviousWatch,
+    ToggleTheme,
+    CommandPalette,
+    Close,
+}
+
+impl Action {
+    pub const ALL: [Self; 8] = [
+        Self::AddFromClipboard,
+        Self::ToggleSelectedWatch,
+        Self::OpenSettings,
+        Self::NextWatch,
+        Self::PreviousWatch,
+        Self::ToggleTheme,
+        Self::CommandPalette,
+        Self::Close,
+    ];
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Action::AddFromClipboard => "Watch beatmap from clipboard",
+            Action::ToggleSelectedWatch => "Start/stop selected watch",
+            Action::OpenSettings => "Open settings",
+            Action::NextWatch => "Select next watch",
+            Action::PreviousWatch => "Select previous watch",
+            Action::ToggleTheme => "Toggle dark/light theme",
+            Action::CommandPalette => "Command palette",
+            Action::Close => "Close window",
+        })
+    }
+}
+
+const KEYS: [(&str, Key); 64] = [
+    ("Down", Key::ArrowDown),
+    ("Left", Key::ArrowLeft),
+    ("Right", Key::ArrowRight),
+    ("Up", Key::ArrowUp),
+    ("Escape", Key::Escape),
+    ("Tab", Key::Tab),
+    ("Backspace", Key::Backspace),
+    ("Enter", Key::Enter),
+    ("Space", Key::Space),
+    ("Insert", Key::Insert),
+    ("Delete", Key::Delete),
+    ("Home", Key::Home),
+    ("End", Key::End),
+    ("PageUp", Key::PageUp),
+    ("PageDown", Key::PageDown),
+    ("0", Key::Num0),
+    ("1", Key::Num1),
+    ("2", Key::Num2),
+    ("3", Key::Num3),
+    ("4", Key::Num4),
+    ("5", Key::Num5),
+    ("6", Key::Num6),
+    ("7", Key::Num7),
+    ("8", Key::Num8),
+    ("9", Key::Num9),
+    ("A", Key::A),
+    ("B", Key::B),
+    ("C", Key::C),
+    ("D", Key::D),
+    ("E", Key::E),
+    ("F", Key::F),
+    ("G", Key::G),
+    ("H", Key::H),
+    ("I", Key::I),
+    ("J", Key::J),
+    ("K", Key::K),
+    ("L", Key::L),
+    ("M", Key::M),
+    ("N", Key::N),
+    ("O", Key::O),
+    ("P", Key::P),
+    ("Q", Key::Q),
+    ("R", Key::R),
+    ("S", Key::S),
+    ("T", Key::T),
+    ("U", Key::U),
+    ("V", Key::V),
+    ("W", Key::W),
+    ("X", Key::X),
+    ("Y", Key::Y),
+    ("Z", Key::Z),
+    ("F1", Key::F1),
+    ("F2", Key::F2),
+    ("F3", Key::F3),
+    ("F4", Key::F4),
+    ("F5", Key::F5),
+    ("F6", Key::F6),
+    ("F7", Key::F7),
+    ("F8", Key::F8),
+    ("F9", Key::F9),
+    ("F10", Key::F10),
+    ("F11", Key::F11),
+    ("F12", Key::F12),
+    ("F13", Key::F13),
+];
+
+#[derive(Clone, Copy, Debug)]
+pub struct Shortcut {
+    pub modifiers: Modifiers,
+    pub key: Key,
+}
+
+impl Shortcut {
+    /// Whether typing in a text field may need the keys, e.g. for `Space`.
+    pub fn is_text_input(self) -> bool {
+        !self.modifiers.command && !self.modifiers.alt
+    }
+}
+
+impl FromStr for Shortcut {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = Modifiers::NONE;
+        let mut key = None;
+        for part in s.split('+').map(str::trim) {
+            match part.to_ascii_lowercase().as_str() {
+                // Ctrl on Windows and Linux, ⌘ on macOS
+                "ctrl" | "cmd" => modifiers.command = true,
+                "shift" => modifiers.shift = true,
+                "alt" => modifiers.alt = true,
+                _ if key.is_none() => {
+                    key = KEYS
+                        .iter()
+                        .find(|(name, _)| name.eq_ignore_ascii_case(part))
+                        .map(|(_, key)| *key);
+                    if key.is_none() {
+                        return Err(format!("Unknown key \"{part}\""));
+                    }
+                }
+                _ => return Err("Only one key allowed".to_string()),
+            }
+        }
+        match key {
+            Some(key) => Ok(Self { modifiers, key }),
+            None => Err("No key".to_string()),
+        }
+    }
+}
+
+impl Display for Shortcut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.modifiers.command {
+            f.write_str(if cfg!(target_os = "macos") {
+                "Cmd+"
+            } else {
+                "Ctrl+"
+            })?;
+        }
+        if self.modifiers.alt {
+            f.write_str("Alt+")?;
+        }
+        if self.modifiers.shift {
+            f.write_str("Shift+")?;
+        }
+        let name = KEYS
+            .iter()
+            .find(|(_, key)| *key == self.key)
+            .map_or("?", |(name, _)| name);
+        f.write_str(name)
+    }
+}
+
+/// Bindings by action, empty if unbound.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Shortcuts {
+    pub add_from_clipboard: String,
+    pub toggle_selected_watch: String,
+    pub open_settings: String,
+    pub next_watch: String,
+    pub previous_watch: String,
+    pub toggle_theme: String,
+    pub command_palette: String,
+    pub close: String,
+}
+
+impl Shortcuts {
+    pub fn binding(&self, action: Action) -> &String {
+        match action {
+            Action::AddFromClipboard => &self.add_from_clipboard,
+            Action::ToggleSelectedWatch => &self.toggle_selected_watch,
+            Action::OpenSettings => &self.open_settings,
+            Action::NextWatch => &self.next_watch,
+            Action::PreviousWatch => &self.previous_watch,
+            Action::ToggleTheme => &self.toggle_theme,
+            Action::CommandPalette => &self.command_palette,
+            Action::Close => &self.close,
+        }
+    }
+
+    pub fn binding_mut(&mut self, action: Action) -> &mut String {
+        match action {
+            Action::AddFromClipboard => &mut self.add_from_clipboard,
+            Action::ToggleSelectedWatch => &mut self.toggle_selected_watch,
+            Action::OpenSettings => &mut self.open_settings,
+            Action::NextWatch => &mut self.next_watch,
+            Action::PreviousWatch => &mut self.previous_watch,
+            Action::ToggleTheme => &mut self.toggle_theme,
+            Action::CommandPalette => &mut self.command_palette,
+            Action::Close => &mut self.close,
+        }
+    }
+}
+
+impl Default for Shortcuts {
+    fn default() -> Self {
+        Self {
+            add_from_clipboard: "Ctrl+Shift+V".to_string(),
+            toggle_selected_watch: "Space".to_string(),
+            open_settings: "Ctrl+O".to_string(),
+            next_watch: "Down".to_string(),
+            previous_watch: "Up".to_string(),
+            toggle_theme: "Ctrl+T".to_string(),
+            command_palette: "Ctrl+P".to_string(),
+            // closing by accident is easy with a single key
+            close: String::new(),
+        }
+    }
+}
+
+/// The bindings parsed, done again whenever they change rather than every
+/// frame.
+#[derive(Default)]
+pub struct Bindings {
+    shortcuts: Vec<(Action, Shortcut)>,
+    errors: Vec<(Action, String)>,
+}
+
+impl Bindings {
+    pub fn new(shortcuts: &Shortcuts) -> Self {
+        let mut bindings = Self::default();
+        for action in Action::ALL {
+            let binding = shortcuts.binding(action);
+            if binding.trim().is_empty() {
+                continue;
+            }
+            match binding.parse() {
+                Ok(shortcut) => bindings.shortcuts.push((action, shortcut)),
+                Err(err) => bindings.errors.push((action, err)),
+            }
+        }
+        bindings
+    }
+
+    /// `None` if unbound or invalid.
+    pub fn get(&self, action: Action) -> Option<Shortcut> {
+        self.shortcuts
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .map(|(_, shortcut)| *shortcut)
+    }
+
+    /// Why the binding of `action` is invalid.
+    pub fn error(&self, action: Action) -> Option<&str> {
+        self.errors
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .map(|(_, err)| err.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Action, Shortcut)> + '_ {
+        self.shortcuts.iter().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<Shortcut, String> {
+        s.parse()
+    }
+
+    #[test]
+    fn parses_modifiers_and_key() {
+        let shortcut = parse("Ctrl+Shift+V").unwrap();
+        assert!(shortcut.modifiers.command && shortcut.modifiers.shift);
+        assert!(!shortcut.modifiers.alt);
+        assert_eq!(shortcut.key, Key::V);
+
+        let shortcut = parse(" alt + f4 ").unwrap();
+        assert!(shortcut.modifiers.alt && !shortcut.modifiers.command);
+        assert_eq!(shortcut.key, Key::F4);
+
+        let shortcut = parse("Space").unwrap();
+        assert_eq!(shortcut.modifiers, Modifiers::NONE);
+        assert_eq!(shortcut.key, Key::Space);
+        assert!(shortcut.is_text_input());
+        assert!(!parse("Cmd+Space").unwrap().is_text_input());
+    }
+
+    #[test]
+    fn rejects_invalid_shortcuts() {
+        assert_eq!(parse("Ctrl+Foo").unwrap_err(), "Unknown key \"Foo\"");
+        assert_eq!(parse("Ctrl+A+B").unwrap_err(), "Only one key allowed");
+        assert_eq!(parse("Ctrl+Shift").unwrap_err(), "No key");
+        assert_eq!(parse("").unwrap_err(), "Unknown key \"\"");
+    }
+
+    #[test]
+    fn displays_what_it_parses() {
+        for binding in ["Alt+Shift+PageDown", "Up", "F13"] {
+            assert_eq!(parse(binding).unwrap().to_string(), binding);
+        }
+    }
+
+    #[test]
+    fn bindings_keep_errors_and_skip_unbound() {
+        let mut shortcuts = Shortcuts {
+            toggle_theme: "Ctrl+Nope".to_string(),
+            ..Shortcuts::default()
+        };
+        shortcuts.close = "  ".to_string();
+        let bindings = Bindings::new(&shortcuts);
+        assert_eq!(bindings.get(Action::NextWatch).unwrap().key, Key::ArrowDown);
+        assert!(bindings.get(Action::ToggleTheme).is_none());
+        assert_eq!(
+            bindings.error(Action::ToggleTheme),
+            Some("Unknown key \"Nope\"")
+        );
+        assert!(bindings.get(Action::Close).is_none());
+        assert!(bindings.error(Action::Close).is_none());
+        assert_eq!(bindings.iter().count(), Action::ALL.len() - 2);
+    }
+}
@@ -0,0 +1,274 @@
+//! System tray icon, shown as a `StatusNotifierItem` on Linux. Other platforms
+//! have no tray icon, the option to close to the tray is hidden there and the
+//! window always closes normally.
+//!
+//! Hiding the window relies on the window manager, Wayland compositors ignore
+//! it and the window stays visible. Tray events wake the UI with a repaint, as
+//! a hidden window isn't repainted otherwise.
+
+use std::sync::mpsc;
+
+use eframe::egui::Context;
+
+use crate::osu::types::RankStatus;
+
+pub enum TrayEvent {
+    Show,
+    Quit,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct TrayWatch {
+    pub title: String,
+    pub ranked: Option<RankStatus>,
+}
+
+/// The most notable status among all watched beatmaps.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Badge {
+    None,
+    Qualified,
+    Ranked,
+}
+
+impl Badge {
+    fn of(watches: &[TrayWatch]) -> Self {
+        watches
+            .iter()
+            .map(|watch| match watch.ranked {
+                Some(RankStatus::Ranked | RankStatus::Approved | RankStatus::Loved) => Self::Ranked,
+                Some(RankStatus::Qualified) => Self::Qualified,
+                _ => Self::None,
+            })
+            .max()
+            .unwrap_or(Self::None)
+    }
+}
+
+pub struct Tray {
+    #[cfg(target_os = "linux")]
+    handle: Option<ksni::blocking::Handle<sni::SniTray>>,
+    rx: mpsc::Receiver<TrayEvent>,
+    watches: Vec<TrayWatch>,
+}
+
+impl Tray {
+    /// Events request a repaint of `ctx` to be handled while hidden.
+    pub fn new(ctx: &Context) -> Self {
+        let (tx, rx) = mpsc::channel();
+
+        #[cfg(target_os = "linux")]
+        {
+            Self {
+                handle: sni::spawn(tx, ctx.clone()),
+                rx,
+                watches: Vec::new(),
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = ctx;
+            drop(tx);
+            Self {
+                rx,
+                watches: Vec::new(),
+            }
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        {
+            self.handle
+                .as_ref()
+                .is_some_and(|handle| !handle.is_closed())
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            false
+        }
+    }
+
+    pub fn set_watches(&mut self, watches: Vec<TrayWatch>) {
+        if watches == self.watches {
+            return;
+        }
+        self.watches = watches;
+
+        #[cfg(target_os = "linux")]
+        if let Some(handle) = &self.handle {
+            let watches = self.watches.clone();
+            handle.update(|tray| tray.watches = watches);
+        }
+    }
+
+    pub fn poll_events(&self) -> mpsc::TryIter<'_, TrayEvent> {
+        self.rx.try_iter()
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod sni {
+    use std::sync::mpsc;
+
+    use eframe::egui::Context;
+    use image::{Rgba, RgbaImage};
+    use ksni::blocking::{Handle, TrayMethods};
+    use ksni::menu::{MenuItem, StandardItem};
+    use ksni::{Icon, Status, ToolTip};
+    use tracing::warn;
+
+    use super::{Badge, TrayEvent, TrayWatch};
+    use crate::gui::App;
+
+    pub struct SniTray {
+        pub watches: Vec<TrayWatch>,
+        icon: Vec<Icon>,
+        tx: mpsc::Sender<TrayEvent>,
+        ctx: Context,
+    }
+
+    impl SniTray {
+        fn send(&self, event: TrayEvent) {
+            if self.tx.send(event).is_ok() {
+                self.ctx.request_repaint();
+            }
+        }
+    }
+
+    pub fn spawn(tx: mpsc::Sender<TrayEvent>, ctx: Context) -> Option<Handle<SniTray>> {
+        let icon = image::load_from_memory(include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/assets/icon.png"
+        )))
+        .unwrap()
+        .into_rgba8();
+
+        let tray = SniTray {
+            watches: Vec::new(),
+            icon: vec![to_icon(&icon)],
+            tx,
+            ctx,
+        };
+        match tray.spawn() {
+            Ok(handle) => Some(handle),
+            Err(err) => {
+                warn!("No tray icon: {err}");
+                None
+            }
+        }
+    }
+
+    /// Converts RGBA pixels to the ARGB32 format used by `StatusNotifierItem`.
+    fn to_icon(image: &RgbaImage) -> Icon {
+        Icon {
+            width: image.width().try_into().unwrap(),
+            height: image.height().try_into().unwrap(),
+            data: image
+                .pixels()
+                .flat_map(|Rgba([r, g, b, a])| [*a, *r, *g, *b])
+                .collect(),
+        }
+    }
+
+    fn badge_icon(color: Rgba<u8>) -> Icon {
+        const SIZE: u32 = 16;
+        let center = f64::from(SIZE) / 2.;
+        to_icon(&RgbaImage::from_fn(SIZE, SIZE, |x, y| {
+            let distance = (f64::from(x) + 0.5 - center).hypot(f64::from(y) + 0.5 - center);
+            if distance <= center {
+                color
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        }))
+    }
+
+    impl ksni::Tray for SniTray {
+        fn id(&self) -> String {
+            env!("CARGO_PKG_NAME").to_string()
+        }
+
+        fn title(&self) -> String {
+            App::NAME.to_string()
+        }
+
+        fn activate(&mut self, _x: i32, _y: i32) {
+            self.send(TrayEvent::Show);
+        }
+
+        fn status(&self) -> Status {
+            match Badge::of(&self.watches) {
+                Badge::Ranked => Status::NeedsAttention,
+                Badge::Qualified | Badge::None => Status::Active,
+            }
+        }
+
+        fn icon_pixmap(&self) -> Vec<Icon> {
+            self.icon.clone()
+        }
+
+        fn attention_icon_pixmap(&self) -> Vec<Icon> {
+            self.icon.clone()
+        }
+
+        fn overlay_icon_pixmap(&self) -> Vec<Icon> {
+            match Badge::of(&self.watches) {
+                Badge::None => Vec::new(),
+                Badge::Qualified => vec![badge_icon(Rgba([102, 204, 255, 255]))],
+                Badge::Ranked => vec![badge_icon(Rgba([0, 255, 0, 255]))],
+            }
+        }
+
+        fn tool_tip(&self) -> ToolTip {
+            ToolTip {
+                title: App::NAME.to_string(),
+                description: format!("Watching {} beatmaps", self.watches.len()),
+                ..Default::default()
+            }
+        }
+
+        fn menu(&self) -> Vec<MenuItem<Self>> {
+            let mut menu = vec![StandardItem {
+                label: "Show".to_string(),
+                activate: Box::new(|tray: &mut Self| {
+                    tray.send(TrayEvent::Show);
+                }),
+                ..Default::default()
+            }
+            .into()];
+
+            if !self.watches.is_empty() {
+                menu.push(MenuItem::Separator);
+                menu.extend(self.watches.iter().map(|watch| {
+                    StandardItem {
+                        label: match watch.ranked {
+                            Some(ranked) => format!("{} — {ranked}", watch.title),
+                            None => watch.title.clone(),
+                        }
+                        // underscores mark access keys in menu labels
+                        .replace('_', "__"),
+                        activate: Box::new(|tray: &mut Self| {
+                            tray.send(TrayEvent::Show);
+                        }),
+                        ..Default::default()
+                    }
+                    .into()
+                }));
+            }
+
+            menu.push(MenuItem::Separator);
+            menu.push(
+                StandardItem {
+                    label: "Quit".to_string(),
+                    activate: Box::new(|tray: &mut Self| {
+                        tray.send(TrayEvent::Quit);
+                    }),
+                    ..Default::default()
+                }
+                .into(),
+            );
+            menu
+        }
+    }
+}
@@ -1,3 +1,4 @@
 pub mod beatmap;
 pub mod hamster;
 pub mod hamster_hack;
+pub mod ruleset;
@@ -1,44 +1,366 @@
-use eframe::egui::{Color32, Response, RichText, Spinner, Ui, Widget};
-use eframe::epaint::{TextureHandle, Vec2};
+use std::time::Instant;
 
-use crate::osu::types::{Beatmap, RankStatus};
+use eframe::egui::{
+    CollapsingHeader, Color32, Layout, ProgressBar, Response, RichText, Sense, Spinner, Ui, Widget,
+};
+use eframe::emath::{Align, Pos2, Rect};
+use eframe::epaint::{Shape, TextureHandle, Vec2};
+
+use super::ruleset::RulesetIcon;
+use crate::gui::client::PollFailure;
+use crate::osu::difficulty::{Mods, PerformanceState, ACCURACIES};
+use crate::osu::download::DownloadState;
+use crate::osu::types::{Beatmap, CoverVariant, RankStatus};
+use crate::osu::watcher::Transition;
+
+pub enum PreviewButton {
+    Stopped,
+    Loading,
+    Playing(f32),
+}
+
+/// Shown when the details may be out of date.
+pub struct Stale<'a> {
+    /// Seconds since the beatmap was last polled, `None` if never.
+    pub age: Option<u64>,
+    /// `None` if the beatmap was restored and not polled yet.
+    pub failure: Option<&'a PollFailure>,
+}
 
 #[allow(clippy::module_name_repetitions)]
 pub struct BeatmapWidget<'a> {
     pub beatmap: &'a Beatmap,
     pub beatmap_cover: Option<TextureHandle>,
+    pub last_transition: Option<&'a Transition>,
+    pub download: Option<&'a DownloadState>,
+    /// `None` when there is no audio output.
+    pub preview: Option<PreviewButton>,
+    pub preview_clicked: &'a mut bool,
     pub worker_running: bool,
+    pub stale: Option<Stale<'a>>,
+    pub performance: Option<&'a PerformanceState>,
+    pub mods: Mods,
+    /// Set to the new mods when they are toggled.
+    pub mods_selected: &'a mut Option<Mods>,
+    /// Draws the cover as a banner behind the details instead of an icon,
+    /// sized like the variant requested for it.
+    pub banner: Option<CoverVariant>,
+}
+
+impl BeatmapWidget<'_> {
+    const BANNER_WIDTH: f32 = 360.;
+
+    /// The narrowest banner cover at least `width` points wide at the display
+    /// scale, the widest if none is.
+    pub fn banner_variant(width: f32, pixels_per_point: f32) -> CoverVariant {
+        let scale = if pixels_per_point > 1. { 2. } else { 1. };
+        let width = width.max(Self::BANNER_WIDTH) * pixels_per_point;
+        #[allow(clippy::cast_precision_loss)]
+        CoverVariant::BANNERS
+            .into_iter()
+            .find(|variant| variant.size().0 as f32 * scale >= width)
+            .unwrap_or(CoverVariant::SlimCover)
+    }
+
+    fn progress(ui: &mut Ui, current: u32, required: Option<u32>, text: String) {
+        #[allow(clippy::cast_precision_loss)]
+        let progress = match required {
+            Some(0) => 1.,
+            Some(required) => current as f32 / required as f32,
+            None => 0.,
+        };
+        ui.add(ProgressBar::new(progress).desired_width(160.).text(text));
+    }
+
+    fn age(seconds: u64) -> String {
+        match seconds {
+            0..=59 => format!("{seconds} s"),
+            60..=3599 => format!("{} min", seconds / 60),
+            3600..=86399 => format!("{} h", seconds / 3600),
+            _ => format!("{} d", seconds / 86400),
+        }
+    }
+
+    fn stale(ui: &mut Ui, stale: &Stale) {
+        let updated = stale.age.map_or_else(
+            || "not updated yet".to_string(),
+            |age| format!("last updated {} ago", Self::age(age)),
+        );
+        let text = match stale.failure {
+            Some(failure) => {
+                let retry = failure.retry_at.map_or_else(
+                    || "stopped watching".to_string(),
+                    |retry_at| {
+                        let retry_in = retry_at.saturating_duration_since(Instant::now());
+                        format!("retrying in {}", Self::age(retry_in.as_secs()))
+                    },
+                );
+                match &failure.error {
+                    Some(error) => format!("⚠ {error}, {retry}, {updated}"),
+                    None => format!("📴 Waiting for network, {retry}, {updated}"),
+                }
+            }
+            None => format!("⚠ Stale, {updated}"),
+        };
+        ui.colored_label(Color32::YELLOW, text);
+    }
+
+    fn performance(&mut self, ui: &mut Ui) {
+        if let Some(performance) = self.performance {
+            ui.horizontal(|ui| {
+                for (mods, acronym) in Mods::ALL {
+                    if ui
+                        .selectable_label(self.mods.contains(mods), RichText::new(acronym).small())
+                        .clicked()
+                    {
+                        *self.mods_selected = Some(self.mods.toggle(mods));
+                    }
+                }
+                match performance {
+                    PerformanceState::Calculating => {
+                        ui.spinner();
+                    }
+                    PerformanceState::Calculated(performance) => {
+                        ui.label(format!(
+                            "★ {:.2} · {}x",
+                            performance.stars, performance.max_combo
+                        ));
+                    }
+                    PerformanceState::Failed(err) => {
+                        ui.colored_label(Color32::LIGHT_RED, format!("pp: {err}"));
+                    }
+                }
+            });
+            if let PerformanceState::Calculated(performance) = performance {
+                let pp = ACCURACIES
+                    .iter()
+                    .zip(performance.pp)
+                    .map(|(accuracy, pp)| {
+                        if *accuracy >= 100. {
+                            format!("SS {pp:.0}pp")
+                        } else {
+                            format!("{accuracy}% {pp:.0}pp")
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                ui.label(pp.join(" · "));
+            }
+        }
+    }
+
+    fn download(&self, ui: &mut Ui) {
+        match self.download {
+            Some(DownloadState::Downloading { downloaded, total }) => {
+                #[allow(clippy::cast_precision_loss)]
+                let progress = total.map_or(0., |total| *downloaded as f32 / total as f32);
+                ui.add(
+                    ProgressBar::new(progress)
+                        .desired_width(160.)
+                        .text(format!("Downloading… {} KiB", downloaded / 1024)),
+                );
+            }
+            Some(DownloadState::Installing) => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Installing…");
+                });
+            }
+            Some(DownloadState::Installed(path)) => {
+                ui.colored_label(Color32::GREEN, "Installed")
+                    .on_hover_text(path.display().to_string());
+            }
+            Some(DownloadState::Failed(err)) => {
+                ui.colored_label(Color32::LIGHT_RED, format!("Download failed: {err}"));
+            }
+            None => (),
+        }
+    }
+
+    fn details(&mut self, ui: &mut Ui) {
+        let beatmapset = &self.beatmap.beatmapset;
+        ui.label(RichText::new(&beatmapset.title).strong());
+        ui.label(&beatmapset.artist);
+        ui.label(&beatmapset.creator);
+        ui.horizontal(|ui| {
+            if self.worker_running {
+                ui.spinner();
+            }
+            if let Some(mode) = self.beatmap.mode {
+                ui.add(RulesetIcon {
+                    mode,
+                    size: 16.,
+                    dimmed: false,
+                    sense: Sense::hover(),
+                });
+            }
+            ui.label(RichText::new(format!("{}", self.beatmap.ranked)).color(
+                match self.beatmap.ranked {
+                    RankStatus::Graveyard | RankStatus::Wip => Color32::GRAY,
+                    RankStatus::Ranked => Color32::GREEN,
+                    RankStatus::Loved => Color32::LIGHT_RED,
+                    _ => Color32::WHITE,
+                },
+            ));
+            match self.preview {
+                Some(PreviewButton::Stopped) => {
+                    *self.preview_clicked = ui.small_button("▶ Preview").clicked();
+                }
+                Some(PreviewButton::Loading) => {
+                    *self.preview_clicked = ui.small_button("⏹").clicked();
+                    ui.spinner();
+                }
+                Some(PreviewButton::Playing(progress)) => {
+                    *self.preview_clicked = ui.small_button("⏹").clicked();
+                    ui.add(ProgressBar::new(progress).desired_width(64.));
+                }
+                None => (),
+            }
+        });
+        if let Some(nominations) = beatmapset.nominations_summary {
+            Self::progress(
+                ui,
+                nominations.current,
+                nominations.required(),
+                format!(
+                    "{}/{} nominations",
+                    nominations.current,
+                    nominations.required_text()
+                ),
+            );
+        }
+        if let Some(hype) = beatmapset.hype {
+            Self::progress(
+                ui,
+                hype.current,
+                Some(hype.required),
+                format!("{}/{} hype", hype.current, hype.required),
+            );
+        }
+        self.performance(ui);
+        if let Some(stale) = &self.stale {
+            Self::stale(ui, stale);
+        }
+        match self.last_transition {
+            Some(Transition::StatusChanged { from, to }) => {
+                ui.label(format!("{from} → {to}"));
+            }
+            Some(Transition::NominationAdded(_)) => {
+                ui.colored_label(Color32::GREEN, "Nomination added");
+            }
+            Some(Transition::NominationRemoved(_)) => {
+                ui.colored_label(Color32::LIGHT_RED, "Nomination removed");
+            }
+            Some(Transition::Updated { from, to }) => {
+                let changes = from.changes(to);
+                if changes.is_empty() {
+                    ui.colored_label(Color32::LIGHT_BLUE, "✏ Updated");
+                } else {
+                    CollapsingHeader::new(RichText::new("✏ Updated").color(Color32::LIGHT_BLUE))
+                        .id_source(("update", self.beatmap.id))
+                        .show(ui, |ui| {
+                            for change in changes {
+                                ui.label(change);
+                            }
+                        });
+                }
+            }
+            None => (),
+        }
+        self.download(ui);
+    }
 }
 
 impl Widget for BeatmapWidget<'_> {
-    fn ui(self, ui: &mut Ui) -> Response {
+    fn ui(mut self, ui: &mut Ui) -> Response {
+        if let Some(variant) = self.banner {
+            return ui
+                .group(|ui| {
+                    let width = ui.available_width().max(Self::BANNER_WIDTH);
+                    #[allow(clippy::cast_precision_loss)]
+                    let aspect_ratio = {
+                        let (width, height) = variant.size();
+                        width as f32 / height as f32
+                    };
+                    let min_rect = Rect::from_min_size(
+                        ui.cursor().min,
+                        Vec2::new(width, width / aspect_ratio),
+                    );
+                    // painted below the details once their height is known
+                    let cover = ui.painter().add(Shape::Noop);
+                    let shade = ui.painter().add(Shape::Noop);
+
+                    let mut details =
+                        ui.child_ui(min_rect.shrink(8.), Layout::top_down(Align::Min));
+                    details.visuals_mut().override_text_color = Some(Color32::WHITE);
+                    self.details(&mut details);
+
+                    // grown to fit long titles, cropping the cover's sides
+                    let rect = min_rect.union(details.min_rect().expand(8.));
+                    ui.allocate_rect(rect, Sense::hover());
+                    if let Some(beatmap_cover) = &self.beatmap_cover {
+                        // the texture may still be another variant's
+                        let visible = rect.aspect_ratio() / beatmap_cover.aspect_ratio();
+                        let uv = Rect::from_center_size(
+                            Pos2::new(0.5, 0.5),
+                            if visible < 1. {
+                                Vec2::new(visible, 1.)
+                            } else {
+                                Vec2::new(1., 1. / visible)
+                            },
+                        );
+                        ui.painter().set(
+                            cover,
+                            Shape::image(beatmap_cover.id(), rect, uv, Color32::WHITE),
+                        );
+                    }
+                    ui.painter().set(
+                        shade,
+                        Shape::rect_filled(rect, 0., Color32::from_black_alpha(160)),
+                    );
+                })
+                .response;
+        }
+
         ui.group(|ui| {
             ui.horizontal(|ui| {
-                match self.beatmap_cover {
-                    Some(beatmap_icon) => ui.image(&beatmap_icon, Vec2::splat(64.)),
+                match &self.beatmap_cover {
+                    Some(beatmap_icon) => ui.image(beatmap_icon, Vec2::splat(64.)),
                     None => ui.add(Spinner::new().size(64.)),
                 };
-                ui.vertical(|ui| {
-                    let beatmapset = &self.beatmap.beatmapset;
-                    ui.label(RichText::new(&beatmapset.title).strong());
-                    ui.label(&beatmapset.artist);
-                    ui.label(&beatmapset.creator);
-                    ui.horizontal(|ui| {
-                        if self.worker_running {
-                            ui.spinner();
-                        }
-                        ui.label(RichText::new(format!("{}", self.beatmap.ranked)).color(
-                            match self.beatmap.ranked {
-                                RankStatus::Graveyard | RankStatus::Wip => Color32::GRAY,
-                                RankStatus::Ranked => Color32::GREEN,
-                                RankStatus::Loved => Color32::LIGHT_RED,
-                                _ => Color32::WHITE,
-                            },
-                        ));
-                    });
-                })
+                ui.vertical(|ui| self.details(ui));
             })
         })
         .response
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::BeatmapWidget;
+    use crate::osu::types::CoverVariant;
+
+    #[test]
+    fn picks_the_narrowest_banner_cover_that_fits() {
+        let variant = BeatmapWidget::banner_variant;
+        assert_eq!(variant(200., 1.), CoverVariant::Card);
+        assert_eq!(variant(400., 1.), CoverVariant::Card);
+        assert_eq!(variant(401., 1.), CoverVariant::Cover);
+        assert_eq!(variant(1000., 1.), CoverVariant::SlimCover);
+        assert_eq!(variant(3000., 1.), CoverVariant::SlimCover);
+        // @2x covers are twice as wide
+        assert_eq!(variant(400., 2.), CoverVariant::Card);
+        assert_eq!(variant(600., 1.5), CoverVariant::Cover);
+    }
+
+    #[test]
+    fn links_high_dpi_covers() {
+        assert_eq!(
+            CoverVariant::SlimCover.url(1, true),
+            "https://assets.ppy.sh/beatmaps/1/covers/slimcover@2x.jpg"
+        );
+        assert_eq!(
+            CoverVariant::Card.url(1, false),
+            "https://assets.ppy.sh/beatmaps/1/covers/card.jpg"
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use eframe::egui::{Response, Sense, Ui, Widget};
+use eframe::epaint::{Pos2, Rect, Stroke, Vec2};
+
+use crate::osu::types::GameMode;
+
+/// Outlines of the osu! ruleset icons, telling the mode on hover.
+#[allow(clippy::module_name_repetitions)]
+pub struct RulesetIcon {
+    pub mode: GameMode,
+    pub size: f32,
+    /// Drawn faded, e.g. for a filter that is off.
+    pub dimmed: bool,
+    /// `Sense::click()` for a button, `Sense::hover()` for a label.
+    pub sense: Sense,
+}
+
+impl Widget for RulesetIcon {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let (rect, response) = ui.allocate_exact_size(Vec2::splat(self.size), self.sense);
+        let visuals = ui.style().interact(&response);
+        let color = if self.dimmed {
+            ui.visuals().weak_text_color()
+        } else {
+            ui.visuals()
+                .override_text_color
+                .unwrap_or(visuals.text_color())
+        };
+        let stroke = Stroke::new(self.size / 12., color);
+        let center = rect.center();
+        let radius = self.size / 2. - stroke.width;
+        let painter = ui.painter();
+
+        painter.circle_stroke(center, radius, stroke);
+        match self.mode {
+            GameMode::Osu => painter.circle_stroke(center, radius * 0.45, stroke),
+            GameMode::Taiko => {
+                painter.circle_stroke(center, radius * 0.55, stroke);
+                painter.line_segment(
+                    [
+                        center - Vec2::new(0., radius * 0.55),
+                        center + Vec2::new(0., radius * 0.55),
+                    ],
+                    stroke,
+                );
+            }
+            GameMode::Fruits => {
+                let offset = radius * 0.3;
+                for fruit in [
+                    Vec2::new(0., -offset),
+                    Vec2::new(-offset, offset * 0.8),
+                    Vec2::new(offset, offset * 0.8),
+                ] {
+                    painter.circle_filled(center + fruit, radius * 0.18, color);
+                }
+            }
+            GameMode::Mania => {
+                let width = radius * 0.16;
+                for (x, height) in [(-0.3, 0.35), (0., 0.55), (0.3, 0.35)] {
+                    let bar_center = Pos2::new(center.x + radius * x, center.y);
+                    painter.rect_filled(
+                        Rect::from_center_size(bar_center, Vec2::new(width, radius * height * 2.)),
+                        width / 2.,
+                        color,
+                    );
+                }
+            }
+        }
+
+        response.on_hover_text(self.mode.to_string())
+    }
+}
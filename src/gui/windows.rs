@@ -1,198 +1,1130 @@
+use std::fmt::Write;
+use std::fs::File;
+use std::path::PathBuf;
+
 use eframe::egui::{
-    self, Area, Button, CentralPanel, Color32, Context, Layout, Order, TextEdit, TopBottomPanel,
-    Visuals, Window,
+    self, Area, Button, CentralPanel, CollapsingHeader, Color32, ComboBox, Context, Grid, Id, Key,
+    LayerId, Layout, Modifiers, Order, RichText, ScrollArea, Sense, Slider, TextEdit, TextStyle,
+    TopBottomPanel, Ui, Visuals, Window,
 };
 use eframe::emath::{Align, Align2};
 use eframe::epaint::Vec2;
 use rand::Rng;
+use tracing::Level;
 
-use self::gui::HamsterHackData;
-use super::widgets::beatmap::BeatmapWidget;
+use self::gui::{CollectionsState, HamsterHackData, Preview};
+use super::client::LoginState;
+use super::config::{CardLayout, Config, Profile};
+use super::palette::{Entry, Palette};
+use super::shortcuts::{Action, Bindings, Shortcuts};
+use super::widgets::beatmap::{BeatmapWidget, PreviewButton, Stale};
 use super::widgets::hamster::HamsterWidget;
 use super::widgets::hamster_hack::HamsterHackWidget;
+use super::widgets::ruleset::RulesetIcon;
 use crate::gui;
-use crate::osu::client::LoginState;
+use crate::osu::api::DEFAULT_BASE_URL;
+use crate::osu::history::{self, ExportContent, ExportFormat};
+use crate::osu::irc::{IrcStatus, Notifier};
+use crate::osu::link::{self, BeatmapLink};
+use crate::osu::rate_limit::MAX_BUDGET;
+use crate::osu::types::{GameMode, RankStatus};
 
 const HAMSTER_OFFSET: f32 = 48.;
 
 impl gui::App {
+    const EXPORT_TITLE: &'static str = "📤 Export History";
+    const IMPORT_TITLE: &'static str = "📂 Import Collection";
+    const LOG_TITLE: &'static str = "📜 Log";
     const SETTINGS_TITLE: &'static str = "⛭ Settings";
 
+    pub fn modal_open(&self) -> bool {
+        self.state.config_open || self.state.import_open || self.state.export_open
+    }
+
     pub fn draw(&mut self, ctx: &Context) {
         if self.state.hamster_hack.is_some() {
             self.draw_hamster_hack(ctx);
         } else {
             self.draw_top_panel(ctx);
             self.draw_main_panel(ctx);
+            self.draw_import(ctx);
+            self.draw_export(ctx);
+            self.draw_log(ctx);
             self.draw_settings(ctx);
+            self.draw_palette(ctx);
             self.draw_hamster(ctx);
+            Self::draw_drop_hint(ctx);
+        }
+    }
+
+    fn draw_drop_hint(ctx: &Context) {
+        if ctx.input().raw.hovered_files.is_empty() {
+            return;
         }
+
+        let painter = ctx.layer_painter(LayerId::new(Order::Foreground, Id::new("drop_hint")));
+        let screen_rect = ctx.input().screen_rect();
+        painter.rect_filled(screen_rect, 0., Color32::from_black_alpha(192));
+        painter.text(
+            screen_rect.center(),
+            Align2::CENTER_CENTER,
+            "Drop .osu files, beatmap folders or .osz archives to watch them",
+            TextStyle::Heading.resolve(&ctx.style()),
+            Color32::WHITE,
+        );
     }
 
     fn draw_top_panel(&mut self, ctx: &Context) {
         TopBottomPanel::top("top_panel").show(ctx, |ui| {
-            ui.set_enabled(!self.state.config_open);
+            ui.set_enabled(!self.modal_open());
             ui.horizontal(|ui| {
                 egui::warn_if_debug_build(ui);
+                if matches!(self.state.login_state, LoginState::WaitingForNetwork)
+                    || self.state.watches.iter().any(|watch| {
+                        watch
+                            .poll_failure
+                            .as_ref()
+                            .is_some_and(|failure| failure.error.is_none())
+                    })
+                {
+                    ui.colored_label(Color32::YELLOW, "📴 Offline");
+                }
+                let stats = self.client.rate_limit_stats();
+                ui.colored_label(
+                    if stats.queued > 0 {
+                        Color32::YELLOW
+                    } else {
+                        Color32::GRAY
+                    },
+                    format!("⏱ {}/{} per minute", stats.recent, stats.budget),
+                )
+                .on_hover_text(format!(
+                    "{} requests available now, {} waiting",
+                    stats.available, stats.queued
+                ));
                 ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
                     if ui.button(Self::SETTINGS_TITLE).clicked() {
                         self.state.config_open = true;
                     }
+                    if ui.button(Self::IMPORT_TITLE).clicked() {
+                        self.state.import_open = true;
+                    }
+                    if ui.button(Self::LOG_TITLE).clicked() {
+                        self.state.log_open = !self.state.log_open;
+                    }
+                    if ui.button(Self::EXPORT_TITLE).clicked() {
+                        self.state.export_open = true;
+                        self.state.export.selected = self.history.beatmap_ids();
+                        self.state.export.result = None;
+                    }
+                    if self.alerts.alarm_active()
+                        && ui
+                            .button(RichText::new("🔕 Acknowledge").color(Color32::GREEN))
+                            .clicked()
+                    {
+                        self.alerts.acknowledge();
+                    }
                 });
             })
         });
     }
 
+    #[allow(clippy::too_many_lines)]
     fn draw_main_panel(&mut self, ctx: &Context) {
         CentralPanel::default().show(ctx, |ui| {
-            ui.set_enabled(!self.state.config_open);
+            ui.set_enabled(!self.modal_open());
             ui.vertical_centered(|ui| {
-                ui.add(
-                    TextEdit::singleline(&mut self.config.beatmap_id)
-                        .interactive(self.state.worker.is_none()),
-                );
+                self.draw_add_watch(ui);
+
+                ui.separator();
 
-                match &self.state.worker {
-                    Some(worker) => {
-                        if ui.button("Stop").clicked() {
-                            worker.abort();
+                ScrollArea::vertical().show(ui, |ui| {
+                    self.state.banner_variant =
+                        BeatmapWidget::banner_variant(ui.available_width(), ctx.pixels_per_point());
+                    let mut started = None;
+                    let mut removed = None;
+                    let mut preview_toggled = None;
+                    let mut mods_selected = None;
+                    let mut selected = None;
+                    let now = history::now();
+                    for (index, watch) in self.state.watches.iter_mut().enumerate() {
+                        if !watch.is_listed(&self.config.hidden_modes) {
+                            continue;
                         }
-                    }
-                    None => {
-                        if let LoginState::LoggedIn { access_token } = &self.state.login_state {
-                            let beatmap_id = self.config.beatmap_id.parse::<u32>();
-                            if ui
-                                .add_enabled(beatmap_id.is_ok(), Button::new("Start"))
-                                .clicked()
-                            {
-                                if let Ok(beatmap_id) = beatmap_id {
-                                    self.state.worker = Some(
-                                        self.client.poll_beatmap(access_token.clone(), beatmap_id),
-                                    );
+                        let row = ui
+                            .horizontal(|ui| {
+                                ui.vertical(|ui| {
+                                    match &watch.worker {
+                                        Some(worker) => {
+                                            if ui.button("⏹ Stop").clicked() {
+                                                worker.abort();
+                                            }
+                                        }
+                                        None => {
+                                            if let LoginState::LoggedIn { .. } =
+                                                self.state.login_state
+                                            {
+                                                if ui.button("▶ Start").clicked() {
+                                                    started = Some(watch.beatmap_id);
+                                                }
+                                            }
+                                        }
+                                    }
+                                    if ui.button("🗑 Remove").clicked() {
+                                        removed = Some(index);
+                                    }
+                                });
+
+                                if let Some(beatmap) = watch.beatmap.as_ref() {
+                                    let mut preview_clicked = false;
+                                    let mut mods = None;
+                                    ui.add(BeatmapWidget {
+                                        beatmap,
+                                        beatmap_cover: watch.beatmap_cover.clone(),
+                                        last_transition: watch.last_transition.as_ref(),
+                                        download: watch.download.as_ref(),
+                                        preview: self.audio.is_available().then(|| {
+                                            match &watch.preview {
+                                                Preview::Stopped => PreviewButton::Stopped,
+                                                Preview::Loading => PreviewButton::Loading,
+                                                Preview::Playing(playback) => {
+                                                    PreviewButton::Playing(playback.progress())
+                                                }
+                                            }
+                                        }),
+                                        preview_clicked: &mut preview_clicked,
+                                        worker_running: watch.worker.is_some(),
+                                        stale: (watch.poll_failure.is_some() || !watch.fresh).then(
+                                            || Stale {
+                                                age: watch
+                                                    .last_updated
+                                                    .map(|updated| now.saturating_sub(updated)),
+                                                failure: watch.poll_failure.as_ref(),
+                                            },
+                                        ),
+                                        performance: watch.performance.as_ref(),
+                                        mods: watch.mods,
+                                        mods_selected: &mut mods,
+                                        banner: (self.config.card_layout == CardLayout::Banner)
+                                            .then_some(self.state.banner_variant),
+                                    });
+                                    if preview_clicked {
+                                        preview_toggled = Some(watch.beatmap_id);
+                                    }
+                                    if let Some(mods) = mods {
+                                        mods_selected = Some((index, mods));
+                                    }
+                                } else {
+                                    ui.label(format!("#{}", watch.beatmap_id));
+                                    if watch.worker.is_some() {
+                                        ui.spinner();
+                                    }
                                 }
+                            })
+                            .response;
+                        if self.state.selected_watch == Some(watch.beatmap_id) {
+                            ui.painter().rect_stroke(
+                                row.rect.expand(2.),
+                                4.,
+                                ui.visuals().selection.stroke,
+                            );
+                            if self.state.scroll_to_selected {
+                                row.scroll_to_me(Some(Align::Center));
                             }
                         }
+                        if row.interact(Sense::click()).clicked() {
+                            selected = Some(watch.beatmap_id);
+                        }
+                    }
+                    self.state.scroll_to_selected = false;
+                    if selected.is_some() {
+                        self.state.selected_watch = selected;
+                    }
+
+                    if let Some(beatmap_id) = started {
+                        self.start_watch(beatmap_id);
+                    }
+                    if let Some((index, mods)) = mods_selected {
+                        self.state.watches[index].mods = mods;
+                    }
+                    if let Some(beatmap_id) = preview_toggled {
+                        self.toggle_preview(beatmap_id);
+                    }
+                    if let Some(index) = removed {
+                        self.remove_watch(index);
                     }
+                });
+            });
+        });
+    }
+
+    fn draw_add_watch(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut self.config.beatmap_id).hint_text("Beatmap ID or link"),
+            );
+            let link = link::parse(&self.config.beatmap_id);
+            if ui
+                .add_enabled(link.is_some(), Button::new("➕ Add"))
+                .clicked()
+            {
+                match link {
+                    Some(BeatmapLink {
+                        beatmap_id: Some(beatmap_id),
+                        ..
+                    }) => self.state.add_watch(beatmap_id),
+                    Some(link) => self.watch_link(link),
+                    None => (),
                 }
+                self.config.beatmap_id.clear();
+            }
+            if ui
+                .button("📋")
+                .on_hover_text("Watch a beatmap link from the clipboard")
+                .clicked()
+            {
+                self.check_clipboard(true);
+            }
+            self.draw_mode_filter(ui);
+        });
 
-                Area::new("beatmap_area")
-                    .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
-                    .order(Order::Background)
-                    .enabled(!self.state.config_open)
-                    .show(ctx, |ui| match self.state.beatmap.as_ref() {
-                        Some(beatmap) => {
-                            ui.add(BeatmapWidget {
-                                beatmap,
-                                beatmap_cover: self.state.beatmap_cover.clone(),
-                                worker_running: self.state.worker.is_some(),
-                            });
+        // hidden once watched by other means
+        let link = self.state.clipboard_link.filter(|link| {
+            !link
+                .beatmap_id
+                .is_some_and(|beatmap_id| self.state.is_watched(beatmap_id))
+        });
+        if let Some(link) = link {
+            ui.horizontal(|ui| {
+                let logged_in = matches!(self.state.login_state, LoginState::LoggedIn { .. });
+                let watch = match (link.beatmap_id, link.beatmapset_id) {
+                    (Some(beatmap_id), _) => {
+                        ui.label(format!("📋 Beatmap #{beatmap_id} in the clipboard"));
+                        ui.button("👁 Watch this")
+                    }
+                    (None, beatmapset_id) => {
+                        ui.label(format!(
+                            "📋 Beatmapset #{} in the clipboard",
+                            beatmapset_id.unwrap_or_default()
+                        ));
+                        ui.add_enabled(logged_in, Button::new("👁 Watch all difficulties"))
+                            .on_disabled_hover_text("Log in first")
+                    }
+                };
+                if watch.clicked() {
+                    self.state.clipboard_link = None;
+                    self.watch_link(link);
+                }
+                if ui.small_button("✖").on_hover_text("Dismiss").clicked() {
+                    self.state.clipboard_link = None;
+                }
+            });
+        }
+        if let Some(notice) = &self.state.notice {
+            let dismissed = ui
+                .horizontal(|ui| {
+                    ui.colored_label(Color32::LIGHT_RED, format!("⚠ {notice}"));
+                    ui.small_button("✖").on_hover_text("Dismiss").clicked()
+                })
+                .inner;
+            if dismissed {
+                self.state.notice = None;
+            }
+        }
+    }
+
+    /// Toggles which modes' watches are listed and which difficulties watching
+    /// a beatmapset adds. Watches of unknown mode are always listed.
+    fn draw_mode_filter(&mut self, ui: &mut Ui) {
+        ui.separator();
+        for mode in GameMode::ALL {
+            let hidden = self.config.hidden_modes.contains(&mode);
+            let response = ui.add(RulesetIcon {
+                mode,
+                size: 20.,
+                dimmed: hidden,
+                sense: Sense::click(),
+            });
+            if response.clicked() {
+                if hidden {
+                    self.config.hidden_modes.retain(|hidden| *hidden != mode);
+                } else {
+                    self.config.hidden_modes.push(mode);
+                }
+            }
+        }
+        let hidden = self
+            .state
+            .watches
+            .iter()
+            .filter(|watch| !watch.is_listed(&self.config.hidden_modes))
+            .count();
+        if hidden > 0 {
+            ui.weak(format!("{hidden} hidden"));
+        }
+    }
+
+    fn draw_palette(&mut self, ctx: &Context) {
+        if let Some(mut palette) = self.state.palette.take() {
+            let picked = self.draw_palette_window(ctx, &mut palette);
+            self.state.palette = Some(palette);
+            if let Some(entry) = picked {
+                self.run_entry(ctx, entry);
+            }
+        }
+    }
+
+    /// Returns the entry picked by clicking or with Enter.
+    fn draw_palette_window(&self, ctx: &Context, palette: &mut Palette) -> Option<Entry> {
+        let mut entries = Action::ALL
+            .iter()
+            .map(|action| {
+                let label = match self.state.bindings.get(*action) {
+                    Some(shortcut) => format!("{action} ({shortcut})"),
+                    None => action.to_string(),
+                };
+                (Entry::Action(*action), label)
+            })
+            .collect::<Vec<_>>();
+        entries.extend(self.state.watches.iter().map(|watch| {
+            let label = match &watch.beatmap {
+                Some(beatmap) => format!(
+                    "#{} {} - {} ({}) [{}]",
+                    watch.beatmap_id,
+                    beatmap.beatmapset.artist,
+                    beatmap.beatmapset.title,
+                    beatmap.beatmapset.creator,
+                    beatmap.ranked
+                ),
+                None => format!("#{}", watch.beatmap_id),
+            };
+            (Entry::Watch(watch.beatmap_id), label)
+        }));
+        let matches = palette.matches(entries);
+
+        {
+            let mut input = ctx.input_mut();
+            if input.consume_key(Modifiers::NONE, Key::ArrowDown) {
+                palette.selected += 1;
+                palette.scroll_to_selected = true;
+            }
+            if input.consume_key(Modifiers::NONE, Key::ArrowUp) {
+                palette.selected = palette.selected.saturating_sub(1);
+                palette.scroll_to_selected = true;
+            }
+        }
+        palette.selected = palette.selected.min(matches.len().saturating_sub(1));
+        let mut picked = ctx
+            .input_mut()
+            .consume_key(Modifiers::NONE, Key::Enter)
+            .then(|| matches.get(palette.selected).map(|(entry, _)| *entry))
+            .flatten();
+
+        Window::new("Command Palette")
+            .title_bar(false)
+            .anchor(Align2::CENTER_TOP, Vec2::new(0., 48.))
+            .collapsible(false)
+            .resizable(false)
+            .fixed_size(Vec2::new(420., 0.))
+            .show(ctx, |ui| {
+                let query = ui.add(
+                    TextEdit::singleline(&mut palette.query)
+                        .hint_text("Search actions and beatmaps…")
+                        .desired_width(f32::INFINITY),
+                );
+                query.request_focus();
+                if query.changed() {
+                    palette.selected = 0;
+                }
+                ui.separator();
+                ScrollArea::vertical().max_height(320.).show(ui, |ui| {
+                    for (index, (entry, label)) in matches.iter().enumerate() {
+                        let response = ui.selectable_label(index == palette.selected, label);
+                        if index == palette.selected && palette.scroll_to_selected {
+                            response.scroll_to_me(None);
                         }
-                        None => {
-                            if self.state.worker.is_some() {
-                                ui.spinner();
-                            }
+                        if response.clicked() {
+                            picked = Some(*entry);
                         }
-                    });
+                    }
+                    if matches.is_empty() {
+                        ui.weak("No matches");
+                    }
+                });
+                palette.scroll_to_selected = false;
             });
-        });
+        picked
     }
 
-    fn draw_settings(&mut self, ctx: &Context) {
-        let mut window = Window::new(Self::SETTINGS_TITLE);
-        if let LoginState::LoggedIn { .. } = self.state.login_state {
-            window = window.open(&mut self.state.config_open);
-        }
-        window
+    fn draw_import(&mut self, ctx: &Context) {
+        let mut import_open = self.state.import_open;
+        let mut imported = None;
+        Window::new(Self::IMPORT_TITLE)
+            .open(&mut import_open)
             .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
             .collapsible(false)
             .auto_sized()
             .default_width(256.)
             .show(ctx, |ui| {
-                let login_inputs_interactive = matches!(
-                    self.state.login_state,
-                    LoginState::LoggedOut | LoginState::LoginError(_)
-                );
-                ui.label("Client ID");
+                ui.label("osu! Folder");
                 ui.add(
-                    TextEdit::singleline(&mut self.config.client_id)
-                        .interactive(login_inputs_interactive)
-                        .hint_text("client_id"),
+                    TextEdit::singleline(&mut self.config.osu_path)
+                        .hint_text("C:\\Users\\…\\AppData\\Local\\osu!"),
                 );
 
-                ui.label("Client Secret");
-                ui.add(
-                    TextEdit::singleline(&mut self.config.client_secret)
-                        .password(true)
-                        .interactive(login_inputs_interactive)
-                        .hint_text("client_secret"),
-                );
+                let loading = matches!(self.state.collections, CollectionsState::Loading);
+                if ui
+                    .add_enabled(
+                        !loading && !self.config.osu_path.is_empty(),
+                        Button::new("🔃 Load Collections"),
+                    )
+                    .clicked()
+                {
+                    self.state.collections = CollectionsState::Loading;
+                    self.client.load_collections(&self.config.osu_path);
+                }
 
-                ui.horizontal(|ui| {
-                    if login_inputs_interactive && ui.button("➡ Log In").clicked() {
-                        self.client.log_in(
-                            self.config.client_id.clone(),
-                            self.config.client_secret.clone(),
-                        );
+                match &self.state.collections {
+                    CollectionsState::NotLoaded => (),
+                    CollectionsState::Loading => {
+                        ui.spinner();
+                    }
+                    CollectionsState::LoadError(err) => {
+                        ui.colored_label(Color32::LIGHT_RED, err);
                     }
+                    CollectionsState::Loaded(collections) if collections.is_empty() => {
+                        ui.label("No collections found");
+                    }
+                    CollectionsState::Loaded(collections) => {
+                        ui.separator();
 
-                    match &self.state.login_state {
-                        LoginState::LoggedOut => (),
-                        LoginState::LoggedIn { .. } => {
-                            if ui.button("⬅ Log Out").clicked() {
-                                self.state.login_state = LoginState::LoggedOut;
-                            }
+                        ComboBox::from_label("Collection").show_index(
+                            ui,
+                            &mut self.state.selected_collection,
+                            collections.len(),
+                            |index| collections[index].name.clone(),
+                        );
+
+                        let collection = &collections[self.state.selected_collection];
+                        ui.label(format!("{} beatmaps", collection.beatmap_ids.len()));
+                        if collection.missing > 0 {
+                            ui.colored_label(
+                                Color32::GRAY,
+                                format!("{} unsubmitted or not in osu!.db", collection.missing),
+                            );
                         }
-                        LoginState::LoggingIn => {
-                            ui.spinner();
-                            ui.label("Logging In…");
+
+                        if ui
+                            .add_enabled(
+                                !collection.beatmap_ids.is_empty(),
+                                Button::new("📥 Import"),
+                            )
+                            .clicked()
+                        {
+                            imported = Some(collection.beatmap_ids.clone());
                         }
-                        LoginState::LoginError(err) => {
-                            ui.colored_label(Color32::LIGHT_RED, err);
+                    }
+                }
+            });
+
+        if let Some(beatmap_ids) = imported {
+            for beatmap_id in beatmap_ids {
+                self.state.add_watch(beatmap_id);
+            }
+            import_open = false;
+        }
+        self.state.import_open = import_open;
+    }
+
+    fn draw_export(&mut self, ctx: &Context) {
+        let mut export_open = self.state.export_open;
+        Window::new(Self::EXPORT_TITLE)
+            .open(&mut export_open)
+            .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+            .collapsible(false)
+            .auto_sized()
+            .default_width(256.)
+            .show(ctx, |ui| {
+                let export = &mut self.state.export;
+                let beatmap_ids = self.history.beatmap_ids();
+                if beatmap_ids.is_empty() {
+                    ui.label("Nothing recorded yet");
+                    return;
+                }
+
+                ScrollArea::vertical().max_height(160.).show(ui, |ui| {
+                    for beatmap_id in beatmap_ids {
+                        let title = self
+                            .history
+                            .observations()
+                            .iter()
+                            .rev()
+                            .find(|observation| observation.beatmap_id == beatmap_id)
+                            .map_or_else(String::new, |observation| {
+                                format!("{} - {}", observation.artist, observation.title)
+                            });
+                        let mut selected = export.selected.contains(&beatmap_id);
+                        if ui
+                            .checkbox(&mut selected, format!("#{beatmap_id} {title}"))
+                            .changed()
+                        {
+                            if selected {
+                                export.selected.push(beatmap_id);
+                            } else {
+                                export.selected.retain(|selected| *selected != beatmap_id);
+                            }
                         }
                     }
                 });
-
                 ui.separator();
 
-                ui.label("Theme");
                 ui.horizontal(|ui| {
-                    let dark_mode = ui.visuals().dark_mode;
-                    if ui.selectable_label(dark_mode, "🌙 Dark").clicked() {
-                        self.config.dark_mode = true;
-                        ctx.set_visuals(Visuals::dark());
+                    for content in ExportContent::ALL {
+                        ui.selectable_value(&mut export.content, content, content.to_string());
                     }
-                    if ui.selectable_label(!dark_mode, "☀ Light").clicked() {
-                        self.config.dark_mode = false;
-                        ctx.set_visuals(Visuals::light());
+                });
+                ui.horizontal(|ui| {
+                    for format in ExportFormat::ALL {
+                        ui.selectable_value(&mut export.format, format, format.to_string());
                     }
                 });
+                ui.label("File");
+                ui.add(
+                    TextEdit::singleline(&mut export.path)
+                        .hint_text(format!("history.{}", export.format.extension())),
+                );
+
+                if ui
+                    .add_enabled(
+                        !export.selected.is_empty() && !export.path.is_empty(),
+                        Button::new("💾 Export"),
+                    )
+                    .clicked()
+                {
+                    let path = PathBuf::from(&export.path);
+                    export.result = Some(
+                        File::create(&path)
+                            .and_then(|file| {
+                                self.history.export(
+                                    &export.selected,
+                                    export.content,
+                                    export.format,
+                                    file,
+                                )
+                            })
+                            .map(|()| path)
+                            .map_err(|err| err.to_string()),
+                    );
+                }
 
-                ui.label("Handedness");
+                match &export.result {
+                    Some(Ok(path)) => {
+                        ui.colored_label(Color32::GREEN, format!("Exported to {}", path.display()));
+                    }
+                    Some(Err(err)) => {
+                        ui.colored_label(Color32::LIGHT_RED, err);
+                    }
+                    None => (),
+                }
+            });
+        self.state.export_open = export_open;
+    }
+
+    fn draw_log(&mut self, ctx: &Context) {
+        let mut log_open = self.state.log_open;
+        Window::new(Self::LOG_TITLE)
+            .open(&mut log_open)
+            .default_size(Vec2::new(480., 240.))
+            .show(ctx, |ui| {
                 ui.horizontal(|ui| {
-                    if ui
-                        .selectable_label(
-                            self.config.hamster_position == Align2::LEFT_BOTTOM,
-                            "◀ Left-Handed",
+                    ComboBox::from_id_source("log_level")
+                        .selected_text(self.state.log_level.as_str())
+                        .show_ui(ui, |ui| {
+                            for level in [Level::ERROR, Level::WARN, Level::INFO, Level::DEBUG] {
+                                ui.selectable_value(
+                                    &mut self.state.log_level,
+                                    level,
+                                    level.as_str(),
+                                );
+                            }
+                        });
+                    ComboBox::from_id_source("log_beatmap")
+                        .selected_text(
+                            self.state
+                                .log_beatmap
+                                .map_or_else(|| "All beatmaps".to_string(), |id| format!("#{id}")),
                         )
-                        .clicked()
-                    {
-                        self.config.hamster_position = Align2::LEFT_BOTTOM;
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.state.log_beatmap, None, "All beatmaps");
+                            for watch in &self.state.watches {
+                                ui.selectable_value(
+                                    &mut self.state.log_beatmap,
+                                    Some(watch.beatmap_id),
+                                    format!("#{}", watch.beatmap_id),
+                                );
+                            }
+                        });
+                    if ui.button("🗑 Clear").clicked() {
+                        self.log.clear();
                     }
-                    if ui
-                        .selectable_label(
-                            self.config.hamster_position == Align2::RIGHT_BOTTOM,
-                            "▶ Right-Handed",
-                        )
-                        .clicked()
-                    {
-                        self.config.hamster_position = Align2::RIGHT_BOTTOM;
+                });
+                ui.separator();
+
+                let entries = self.log.entries(|entry| {
+                    // more verbose levels compare greater
+                    entry.level <= self.state.log_level
+                        && self
+                            .state
+                            .log_beatmap
+                            .is_none_or(|beatmap_id| entry.beatmap_id == Some(beatmap_id))
+                });
+                ScrollArea::both().stick_to_bottom(true).show(ui, |ui| {
+                    for entry in entries {
+                        ui.horizontal(|ui| {
+                            ui.monospace(entry.time_of_day()).on_hover_text("UTC");
+                            ui.colored_label(
+                                match entry.level {
+                                    Level::ERROR => Color32::LIGHT_RED,
+                                    Level::WARN => Color32::YELLOW,
+                                    _ => Color32::GRAY,
+                                },
+                                entry.level.as_str(),
+                            );
+                            if let Some(beatmap_id) = entry.beatmap_id {
+                                ui.label(format!("#{beatmap_id}"));
+                            }
+                            ui.label(&entry.message);
+                        });
                     }
                 });
+            });
+        self.state.log_open = log_open;
+    }
+
+    fn draw_settings(&mut self, ctx: &Context) {
+        let mut config_open = self.state.config_open;
+        let mut window = Window::new(Self::SETTINGS_TITLE);
+        if let LoginState::LoggedIn { .. } = self.state.login_state {
+            window = window.open(&mut config_open);
+        }
+        window
+            .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+            .collapsible(false)
+            .auto_sized()
+            .default_width(256.)
+            .show(ctx, |ui| {
+                self.draw_login_settings(ui);
+
+                ui.separator();
+
+                self.draw_download_settings(ui);
+
+                ui.separator();
+
+                self.draw_alert_settings(ui);
+
+                ui.separator();
+
+                self.draw_appearance_settings(ctx, ui);
+
+                ui.separator();
+
+                self.draw_shortcut_settings(ui);
+
+                ui.separator();
+
+                self.draw_server_settings(ui);
+
+                ui.separator();
+
+                self.draw_irc_settings(ui);
+
+                ui.separator();
+
+                self.draw_config_file_settings(ctx, ui);
 
                 ui.separator();
 
                 ui.hyperlink_to("Help!", "https://youtu.be/9oyC4ArBf1Y");
             });
+        self.state.config_open = config_open;
+    }
+
+    fn draw_server_settings(&mut self, ui: &mut Ui) {
+        if let Some(feed_path) = &self.feed_path {
+            ui.label("Status Feed");
+            ui.label(RichText::new(feed_path.display().to_string()).weak());
+        }
+
+        ui.label("HTTP Endpoint");
+        let response = ui
+            .add(TextEdit::singleline(&mut self.config.serve_address).hint_text("127.0.0.1:9898"));
+        if response.lost_focus() && self.config.serve_address != self.state.served_address {
+            self.restart_server();
+        }
+        if let Some(server) = &self.server {
+            ui.hyperlink(format!("http://{}/metrics", server.address()));
+            ui.hyperlink(format!("http://{}/feed.atom", server.address()));
+        } else if let Some(err) = &self.state.server_error {
+            ui.colored_label(Color32::LIGHT_RED, err);
+        }
+    }
+
+    fn draw_irc_settings(&mut self, ui: &mut Ui) {
+        let mut changed = ui
+            .checkbox(
+                &mut self.config.irc_enabled,
+                "Announce status changes over IRC",
+            )
+            .changed();
+        ui.add_enabled_ui(self.config.irc_enabled, |ui| {
+            let irc = &mut self.config.irc;
+            ui.label("Server");
+            changed |= ui
+                .add(TextEdit::singleline(&mut irc.server).hint_text("irc.ppy.sh:6667"))
+                .lost_focus();
+            ui.label("Nick");
+            changed |= ui.text_edit_singleline(&mut irc.nick).lost_focus();
+            ui.label("Password");
+            changed |= ui
+                .add(TextEdit::singleline(&mut irc.password).password(true))
+                .lost_focus();
+            ui.label("Send To");
+            changed |= ui
+                .add(TextEdit::singleline(&mut irc.target).hint_text("#channel or nick"))
+                .lost_focus();
+        });
+        if changed {
+            self.restart_irc();
+        }
+        let status = self.irc.as_ref().map(Notifier::status);
+        match status {
+            Some(IrcStatus::Connected) => {
+                ui.label("Connected");
+            }
+            Some(IrcStatus::Connecting) => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Connecting…");
+                });
+            }
+            Some(IrcStatus::Refused(reason)) => {
+                ui.horizontal(|ui| {
+                    ui.colored_label(Color32::RED, format!("Refused: {reason}"));
+                    if ui.button("Retry").clicked() {
+                        // connects again although the settings are unchanged
+                        self.state.irc_config = None;
+                        self.restart_irc();
+                    }
+                });
+            }
+            None => (),
+        }
+    }
+
+    fn draw_config_file_settings(&mut self, ctx: &Context, ui: &mut Ui) {
+        ui.label("Settings File");
+        ui.add(TextEdit::singleline(&mut self.state.config_file.path).hint_text("watcher.toml"));
+        ui.checkbox(
+            &mut self.state.config_file.include_secrets,
+            "Include client secret",
+        );
+
+        let path = PathBuf::from(&self.state.config_file.path);
+        ui.horizontal(|ui| {
+            ui.set_enabled(!self.state.config_file.path.is_empty());
+            if ui.button("📥 Import").clicked() {
+                self.state.config_file.result = Some(match Config::load_file(&path) {
+                    Ok(config) => {
+                        self.apply_config(ctx, config);
+                        Ok(format!("Imported {}", path.display()))
+                    }
+                    Err(err) => Err(err.to_string()),
+                });
+            }
+            if ui.button("📤 Export").clicked() {
+                self.sync_watchlist();
+                self.state.config_file.result = Some(
+                    self.config
+                        .save_file(&path, self.state.config_file.include_secrets)
+                        .map(|()| format!("Exported to {}", path.display()))
+                        .map_err(|err| err.to_string()),
+                );
+            }
+        });
+
+        match &self.state.config_file.result {
+            Some(Ok(message)) => {
+                ui.colored_label(Color32::GREEN, message);
+            }
+            Some(Err(err)) => {
+                ui.colored_label(Color32::LIGHT_RED, err);
+            }
+            None => (),
+        }
+    }
+
+    fn draw_profile_switcher(&mut self, ui: &mut Ui) {
+        ui.label("Profile");
+        ui.horizontal(|ui| {
+            let mut profile = self.config.profile;
+            let profiles = &self.config.profiles;
+            ComboBox::from_id_source("profile").show_index(
+                ui,
+                &mut profile,
+                profiles.len(),
+                |index| profiles[index].name.clone(),
+            );
+            if ui.button("➕").on_hover_text("New profile").clicked() {
+                self.config.profiles.push(Profile {
+                    name: format!("Profile {}", self.config.profiles.len() + 1),
+                    ..Profile::default()
+                });
+                profile = self.config.profiles.len() - 1;
+            }
+            if ui
+                .add_enabled(self.config.profiles.len() > 1, Button::new("🗑"))
+                .on_hover_text("Delete profile")
+                .clicked()
+            {
+                self.config.profiles.remove(profile);
+                self.config.profile = profile.saturating_sub(1);
+                self.log_in_again();
+            } else {
+                self.switch_profile(profile);
+            }
+        });
+    }
+
+    fn draw_login_settings(&mut self, ui: &mut Ui) {
+        self.draw_profile_switcher(ui);
+
+        let login_inputs_interactive = matches!(
+            self.state.login_state,
+            LoginState::LoggedOut | LoginState::LoginError(_)
+        );
+        let profile = self.config.profile_mut();
+        ui.label("Name");
+        ui.text_edit_singleline(&mut profile.name);
+
+        ui.label("Client ID");
+        ui.add(
+            TextEdit::singleline(&mut profile.client_id)
+                .interactive(login_inputs_interactive)
+                .hint_text("client_id"),
+        );
+
+        ui.label("Client Secret");
+        ui.add(
+            TextEdit::singleline(&mut profile.client_secret)
+                .password(true)
+                .interactive(login_inputs_interactive)
+                .hint_text("client_secret"),
+        );
+
+        ui.label("API URL");
+        ui.add(
+            TextEdit::singleline(&mut profile.api_url)
+                .interactive(login_inputs_interactive)
+                .hint_text(DEFAULT_BASE_URL),
+        );
+
+        ui.label("Rate Limit");
+        if ui
+            .add(
+                Slider::new(&mut self.config.rate_limit, 1..=MAX_BUDGET).suffix(" requests/minute"),
+            )
+            .changed()
+        {
+            self.client.set_rate_limit(self.config.rate_limit);
+        }
+
+        let profile = self.config.profile_mut();
+        ui.label("Stop watching when")
+            .on_hover_text("Applies to watches started afterwards");
+        ui.horizontal_wrapped(|ui| {
+            for status in RankStatus::ALL {
+                let mut stop = profile.stop_statuses.contains(&status);
+                if ui.checkbox(&mut stop, status.to_string()).changed() {
+                    if stop {
+                        profile.stop_statuses.push(status);
+                    } else {
+                        profile.stop_statuses.retain(|stop| *stop != status);
+                    }
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if login_inputs_interactive && ui.button("➡ Log In").clicked() {
+                self.log_in();
+            }
+
+            match &self.state.login_state {
+                LoginState::LoggedOut => (),
+                LoginState::LoggedIn { .. } => {
+                    if ui.button("⬅ Log Out").clicked() {
+                        self.state.login_state = LoginState::LoggedOut;
+                    }
+                }
+                LoginState::LoggingIn => {
+                    ui.spinner();
+                    ui.label("Logging In…");
+                }
+                LoginState::WaitingForNetwork => {
+                    ui.spinner();
+                    ui.label("Waiting for network…");
+                }
+                LoginState::LoginError(err) => {
+                    ui.colored_label(Color32::LIGHT_RED, err);
+                }
+            }
+        });
+    }
+
+    fn draw_download_settings(&mut self, ui: &mut Ui) {
+        ui.checkbox(
+            &mut self.config.auto_download,
+            "Download when Ranked or Loved",
+        );
+        ui.add_enabled_ui(self.config.auto_download, |ui| {
+            ui.label("Download Mirror");
+            ui.add(
+                TextEdit::singleline(&mut self.config.download_mirror)
+                    .hint_text("https://api.nerinyan.moe/d"),
+            );
+
+            ui.label("osu! Songs Folder");
+            ui.add(
+                TextEdit::singleline(&mut self.config.songs_path)
+                    .hint_text("C:\\Users\\…\\AppData\\Local\\osu!\\Songs"),
+            );
+        });
+    }
+
+    fn draw_alert_settings(&mut self, ui: &mut Ui) {
+        ui.label("Alert Sounds");
+        if !self.audio.is_available() {
+            ui.colored_label(Color32::GRAY, "No audio output available");
+        }
+
+        let mut tested = None;
+        Grid::new("alert_sounds").show(ui, |ui| {
+            for sound in &mut self.config.alert_sounds {
+                ui.checkbox(&mut sound.enabled, sound.status.to_string());
+                ui.add_enabled_ui(sound.enabled, |ui| {
+                    ui.add(
+                        TextEdit::singleline(&mut sound.path)
+                            .hint_text("Bundled sound")
+                            .desired_width(128.),
+                    );
+                    ui.add(Slider::new(&mut sound.volume, 0.0..=1.0).show_value(false));
+                    if ui.button("▶").on_hover_text("Test").clicked() {
+                        tested = Some(sound.status);
+                    }
+                });
+                ui.end_row();
+            }
+        });
+        if let Some(status) = tested {
+            self.alerts.test(&self.audio, &self.config, status);
+        }
+
+        ui.checkbox(
+            &mut self.config.repeat_ranked_alert,
+            "Repeat Ranked alert until acknowledged",
+        );
+
+        ui.horizontal(|ui| {
+            ui.label("Preview Volume");
+            ui.add(Slider::new(&mut self.config.preview_volume, 0.0..=1.0).show_value(false));
+        });
+    }
+
+    fn draw_shortcut_settings(&mut self, ui: &mut Ui) {
+        CollapsingHeader::new("Keyboard Shortcuts").show(ui, |ui| {
+            let mut changed = false;
+            Grid::new("shortcuts").num_columns(3).show(ui, |ui| {
+                for action in Action::ALL {
+                    ui.label(action.to_string());
+                    let error = self.state.bindings.error(action);
+                    changed |= ui
+                        .add(
+                            TextEdit::singleline(self.config.shortcuts.binding_mut(action))
+                                .hint_text("Unbound")
+                                .desired_width(120.)
+                                .text_color_opt(error.is_some().then_some(Color32::LIGHT_RED)),
+                        )
+                        .changed();
+                    if let Some(error) = error {
+                        ui.colored_label(Color32::LIGHT_RED, error);
+                    }
+                    ui.end_row();
+                }
+            });
+            if ui.button("Reset to defaults").clicked() {
+                self.config.shortcuts = Shortcuts::default();
+                changed = true;
+            }
+            if changed {
+                self.state.bindings = Bindings::new(&self.config.shortcuts);
+            }
+        });
+    }
+
+    fn draw_appearance_settings(&mut self, ctx: &Context, ui: &mut Ui) {
+        // there is no tray on other platforms or without a tray host
+        if self.tray.is_available() {
+            ui.checkbox(
+                &mut self.config.close_to_tray,
+                "Keep watching in the tray when closed",
+            );
+        }
+        ui.checkbox(
+            &mut self.config.watch_clipboard,
+            "Offer to watch beatmap links copied to the clipboard",
+        );
+
+        ui.label("Theme");
+        ui.horizontal(|ui| {
+            let dark_mode = ui.visuals().dark_mode;
+            if ui.selectable_label(dark_mode, "🌙 Dark").clicked() {
+                self.config.dark_mode = true;
+                ctx.set_visuals(Visuals::dark());
+            }
+            if ui.selectable_label(!dark_mode, "☀ Light").clicked() {
+                self.config.dark_mode = false;
+                ctx.set_visuals(Visuals::light());
+            }
+        });
+
+        ui.label("Beatmap cards");
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.config.card_layout, CardLayout::Compact, "Compact");
+            ui.selectable_value(&mut self.config.card_layout, CardLayout::Banner, "Banner");
+        });
+
+        ui.label("Handedness");
+        ui.horizontal(|ui| {
+            if ui
+                .selectable_label(
+                    self.config.hamster_position == Align2::LEFT_BOTTOM,
+                    "◀ Left-Handed",
+                )
+                .clicked()
+            {
+                self.config.hamster_position = Align2::LEFT_BOTTOM;
+            }
+            if ui
+                .selectable_label(
+                    self.config.hamster_position == Align2::RIGHT_BOTTOM,
+                    "▶ Right-Handed",
+                )
+                .clicked()
+            {
+                self.config.hamster_position = Align2::RIGHT_BOTTOM;
+            }
+        });
     }
 
     pub fn draw_hamster(&mut self, ctx: &Context) {
@@ -213,11 +1145,13 @@ impl gui::App {
                             let random_bytes = rng.gen::<[u8; 16]>();
                             random_bytes
                                 .iter()
-                                .map(|byte| format!("{byte:02x}"))
-                                .collect()
+                                .fold(String::new(), |mut address, byte| {
+                                    write!(address, "{byte:02x}").unwrap();
+                                    address
+                                })
                         },
                     });
-                };
+                }
             });
     }
 
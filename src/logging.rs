@@ -0,0 +1,137 @@
+//! Logs to standard error, a daily rotated file in the data directory and a
+//! buffer shown in the log window.
+
+use std::collections::VecDeque;
+use std::fmt::{Debug, Write};
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer};
+
+/// Entries kept for the log window.
+const CAPACITY: usize = 1000;
+/// Daily log files kept.
+const MAX_LOG_FILES: usize = 7;
+
+#[derive(Clone)]
+pub struct LogEntry {
+    /// Unix time in seconds.
+    pub time: u64,
+    pub level: Level,
+    pub message: String,
+    pub beatmap_id: Option<u32>,
+}
+
+impl LogEntry {
+    /// The time of day as `HH:MM:SS` in UTC.
+    pub fn time_of_day(&self) -> String {
+        let seconds = self.time % (24 * 60 * 60);
+        format!(
+            "{:02}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct LogBuffer(Arc<Mutex<VecDeque<LogEntry>>>);
+
+impl LogBuffer {
+    /// Copies the entries matching `filter`, which must not log. The lock is
+    /// never held outside, as events logged meanwhile would deadlock.
+    pub fn entries(&self, filter: impl Fn(&LogEntry) -> bool) -> Vec<LogEntry> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|entry| filter(entry))
+            .cloned()
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+/// Collects the message, `beatmap_id` and other fields of an event.
+#[derive(Default)]
+struct EventVisitor {
+    message: String,
+    fields: String,
+    beatmap_id: Option<u32>,
+}
+
+impl Visit for EventVisitor {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if field.name() == "beatmap_id" {
+            self.beatmap_id = value.try_into().ok();
+        } else {
+            self.record_debug(field, &value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            write!(self.message, "{value:?}").unwrap();
+        } else {
+            write!(self.fields, " {}={value:?}", field.name()).unwrap();
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for LogBuffer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = EventVisitor::default();
+        event.record(&mut visitor);
+
+        let mut entries = self.0.lock().unwrap();
+        if entries.len() == CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back(LogEntry {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs()),
+            level: *event.metadata().level(),
+            message: visitor.message + &visitor.fields,
+            beatmap_id: visitor.beatmap_id,
+        });
+    }
+}
+
+/// Installs the global subscriber, filtered by `RUST_LOG` if set.
+pub fn init(app_name: &str) -> LogBuffer {
+    let buffer = LogBuffer::default();
+
+    let file = directories_next::ProjectDirs::from("", "", app_name).and_then(|dirs| {
+        RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix("watcher")
+            .filename_suffix("log")
+            .max_log_files(MAX_LOG_FILES)
+            .build(dirs.data_dir().join("logs"))
+            .map_err(|err| eprintln!("{err:?}"))
+            .ok()
+    });
+
+    tracing_subscriber::registry()
+        .with(
+            EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new("info,osu_beatmap_watcher=debug")),
+        )
+        .with(fmt::layer().with_writer(io::stderr))
+        .with(file.map(|file| fmt::layer().with_ansi(false).with_writer(file)))
+        .with(buffer.clone())
+        .init();
+
+    buffer
+}
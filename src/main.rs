@@ -1,14 +1,30 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console on Windows
 #![warn(clippy::pedantic)]
 
+use std::env;
+use std::process::ExitCode;
+
 use eframe::epaint::Vec2;
 use eframe::{IconData, NativeOptions};
 use gui::App;
+use osu_beatmap_watcher::osu;
 
+mod cli;
 mod gui;
-mod osu;
+mod logging;
+
+fn main() -> ExitCode {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let options = match cli::run(&args) {
+        Ok(Some(options)) => options,
+        Ok(None) => return ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let log = logging::init(App::NAME);
 
-fn main() {
     let icon = image::load_from_memory(include_bytes!(concat!(
         env!("CARGO_MANIFEST_DIR"),
         "/assets/icon.png"
@@ -29,6 +45,7 @@ fn main() {
             min_window_size: Some(Vec2::new(400., 200.)),
             ..Default::default()
         },
-        Box::new(|cc| Box::new(App::new(cc))),
+        Box::new(|cc| Box::new(App::new(cc, options, log))),
     );
+    ExitCode::SUCCESS
 }
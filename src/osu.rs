@@ -1,3 +1,20 @@
-pub mod client;
+pub mod api;
+mod batch;
+pub mod beatmap_file;
+pub mod db;
+pub mod difficulty;
+pub mod download;
+pub mod feed;
+pub mod history;
 mod http;
+pub mod irc;
+pub mod link;
+pub mod metrics;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+pub mod rate_limit;
 pub mod types;
+pub mod watcher;
+
+pub use api::{ApiError, OsuApi};
+pub use watcher::{WatchEvent, Watcher};
@@ -0,0 +1,336 @@
+//! Async access to the osu! API v2, for the GUI and other tools.
+
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use image::RgbaImage;
+use reqwest::StatusCode;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::info;
+
+use super::batch::Batcher;
+use super::download::DownloadError;
+use super::http::Http;
+use super::metrics::Metrics;
+use super::rate_limit::RateLimitStats;
+use super::types::{Beatmap, Beatmapset, CoverVariant, RankStatus};
+use super::watcher::Watcher;
+
+pub const DEFAULT_BASE_URL: &str = "https://osu.ppy.sh";
+
+/// How long before it expires an access token is renewed.
+const TOKEN_RENEWAL_MARGIN: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug)]
+pub enum ApiError {
+    /// The request needs an access token, see [`OsuApi::log_in`].
+    NotLoggedIn,
+    /// Shared, as a failed batch fails all its lookups.
+    Http(Arc<reqwest::Error>),
+}
+
+impl ApiError {
+    /// `None` if there was no response.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            ApiError::NotLoggedIn => None,
+            ApiError::Http(err) => err.status(),
+        }
+    }
+
+    /// Whether the API could not be reached, so retrying later may help.
+    pub fn is_network(&self) -> bool {
+        matches!(self, ApiError::Http(err) if err.status().is_none())
+    }
+
+    /// Whether the API responded with an error which may go away by itself,
+    /// like rate limiting or maintenance.
+    pub fn is_temporary(&self) -> bool {
+        self.status().is_some_and(|status| {
+            status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+        })
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::NotLoggedIn => f.write_str("Not logged in"),
+            ApiError::Http(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(err: reqwest::Error) -> Self {
+        Self::Http(Arc::new(err))
+    }
+}
+
+/// Delay before retrying after a failed request, doubling up to a minute.
+pub struct Backoff(Duration);
+
+impl Backoff {
+    const INITIAL: Duration = Duration::from_secs(2);
+    const MAX: Duration = Duration::from_secs(60);
+
+    pub fn new() -> Self {
+        Self(Self::INITIAL)
+    }
+
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.0;
+        self.0 = (self.0 * 2).min(Self::MAX);
+        delay
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Token {
+    access_token: Arc<str>,
+    expires_at: Instant,
+}
+
+/// The credentials of a logged in handle, to get a new access token when the
+/// current one expires or is rejected.
+struct Session {
+    client_id: String,
+    client_secret: String,
+    token: Mutex<Token>,
+}
+
+impl Session {
+    async fn log_in(
+        http: &Http,
+        client_id: &str,
+        client_secret: &str,
+    ) -> Result<Token, reqwest::Error> {
+        let response = http.get_access_token(client_id, client_secret).await?;
+        Ok(Token {
+            access_token: response.access_token.into(),
+            expires_at: Instant::now()
+                + Duration::from_secs(response.expires_in.try_into().unwrap_or(0)),
+        })
+    }
+
+    /// The current access token, renewed if it is about to expire.
+    async fn access_token(&self, http: &Http) -> Result<Arc<str>, ApiError> {
+        let mut token = self.token.lock().await;
+        if Instant::now() + TOKEN_RENEWAL_MARGIN >= token.expires_at {
+            info!("Access token expired, logging in again");
+            *token = Self::log_in(http, &self.client_id, &self.client_secret).await?;
+            http.metrics.record_token_refresh();
+        }
+        Ok(token.access_token.clone())
+    }
+
+    /// Replaces a rejected access token, unless another request already did.
+    async fn renew(&self, http: &Http, rejected: &str) -> Result<Arc<str>, ApiError> {
+        let mut token = self.token.lock().await;
+        if *token.access_token == *rejected {
+            info!("Access token rejected, logging in again");
+            *token = Self::log_in(http, &self.client_id, &self.client_secret).await?;
+            http.metrics.record_token_refresh();
+        }
+        Ok(token.access_token.clone())
+    }
+}
+
+/// A handle to the API. Clones share the rate limit, the asset cache and the
+/// batching of beatmap lookups.
+///
+/// ```
+/// use osu_beatmap_watcher::osu::mock::{self, MockServer};
+/// use osu_beatmap_watcher::osu::types::RankStatus;
+/// use osu_beatmap_watcher::osu::OsuApi;
+///
+/// let server = MockServer::start().unwrap();
+/// server.set_beatmap(mock::beatmap(75, 1, RankStatus::Ranked));
+///
+/// tokio::runtime::Runtime::new().unwrap().block_on(async {
+///     let api = OsuApi::new(server.url())
+///         .log_in("id", "secret")
+///         .await
+///         .unwrap();
+///     let beatmap = api.beatmap(75).await.unwrap().unwrap();
+///     assert_eq!(beatmap.ranked, RankStatus::Ranked);
+///     assert!(api.beatmap(76).await.unwrap().is_none());
+/// });
+/// ```
+#[derive(Clone)]
+pub struct OsuApi {
+    http: Http,
+    /// Spawned on the runtime of the first lookup.
+    batcher: Arc<OnceLock<Batcher>>,
+    session: Option<Arc<Session>>,
+}
+
+impl Default for OsuApi {
+    fn default() -> Self {
+        Self::new(DEFAULT_BASE_URL)
+    }
+}
+
+impl OsuApi {
+    pub fn new(base_url: &str) -> Self {
+        let mut http = Http::new();
+        http.set_base_url(base_url);
+        Self {
+            http,
+            batcher: Arc::default(),
+            session: None,
+        }
+    }
+
+    /// A handle for another API which is not logged in yet, but shares this
+    /// one's rate limit and caches.
+    #[must_use]
+    pub fn with_base_url(&self, base_url: &str) -> Self {
+        let mut api = self.clone();
+        api.http.set_base_url(base_url);
+        api.session = None;
+        api
+    }
+
+    pub fn base_url(&self) -> &str {
+        self.http.base_url()
+    }
+
+    pub fn is_logged_in(&self) -> bool {
+        self.session.is_some()
+    }
+
+    /// Gets an access token with OAuth client credentials, returning a handle
+    /// which uses it. The handle logs in again with the same credentials when
+    /// the token expires.
+    pub async fn log_in(&self, client_id: &str, client_secret: &str) -> Result<Self, ApiError> {
+        let token = Session::log_in(&self.http, client_id, client_secret).await?;
+        let mut api = self.clone();
+        api.session = Some(Arc::new(Session {
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            token: Mutex::new(token),
+        }));
+        Ok(api)
+    }
+
+    /// Sends a request with the access token, retrying once with a new token
+    /// if it was rejected.
+    async fn authorized<T, F, R>(&self, request: F) -> Result<T, ApiError>
+    where
+        F: Fn(Arc<str>) -> R,
+        R: Future<Output = Result<T, ApiError>>,
+    {
+        let session = self.session.as_ref().ok_or(ApiError::NotLoggedIn)?;
+        let access_token = session.access_token(&self.http).await?;
+        match request(access_token.clone()).await {
+            Err(err) if err.status() == Some(StatusCode::UNAUTHORIZED) => {
+                request(session.renew(&self.http, &access_token).await?).await
+            }
+            result => result,
+        }
+    }
+
+    /// Looks up a beatmap, combined with concurrent lookups into one request.
+    /// Nominations and hype are only included by [`OsuApi::beatmapset`].
+    pub async fn beatmap(&self, beatmap_id: u32) -> Result<Option<Beatmap>, ApiError> {
+        let batcher = self.batcher.get_or_init(Batcher::spawn);
+        self.authorized(|access_token| async move {
+            batcher
+                .get_beatmap(&self.http, beatmap_id, &access_token)
+                .await
+        })
+        .await
+    }
+
+    /// Looks up many beatmaps at once, leaving out those which do not exist.
+    pub async fn beatmaps(&self, beatmap_ids: &[u32]) -> Result<Vec<Beatmap>, ApiError> {
+        self.authorized(|access_token| async move {
+            Ok(self.http.get_beatmaps(beatmap_ids, access_token).await?)
+        })
+        .await
+    }
+
+    pub async fn beatmapset(&self, beatmapset_id: u32) -> Result<Option<Beatmapset>, ApiError> {
+        self.authorized(|access_token| async move {
+            Ok(self
+                .http
+                .get_beatmapset(beatmapset_id, access_token)
+                .await?)
+        })
+        .await
+    }
+
+    /// `None` if the beatmapset has no cover. Does not need logging in.
+    pub async fn beatmap_cover(
+        &self,
+        beatmapset_id: u32,
+        variant: CoverVariant,
+        high_dpi: bool,
+    ) -> Result<Option<RgbaImage>, ApiError> {
+        Ok(self
+            .http
+            .get_beatmap_cover(beatmapset_id, variant, high_dpi)
+            .await?)
+    }
+
+    /// The MP3 preview of a beatmapset. Does not need logging in.
+    pub async fn beatmap_preview(&self, beatmapset_id: u32) -> Result<Option<Arc<[u8]>>, ApiError> {
+        Ok(self.http.get_beatmap_preview(beatmapset_id).await?)
+    }
+
+    /// The `.osu` file of a beatmap's current version, for
+    /// [`super::difficulty::calculate`]. Does not need logging in.
+    pub async fn beatmap_file(&self, beatmap_id: u32) -> Result<Option<Vec<u8>>, ApiError> {
+        Ok(self.http.get_beatmap_file(beatmap_id).await?)
+    }
+
+    /// Downloads the `.osz` of a beatmapset from a mirror serving them at
+    /// `{mirror_url}/{beatmapset_id}`, see [`super::download::install`].
+    pub async fn download_beatmapset(
+        &self,
+        mirror_url: &str,
+        beatmapset_id: u32,
+        on_progress: impl FnMut(u64, Option<u64>),
+    ) -> Result<Vec<u8>, DownloadError> {
+        self.http
+            .download_beatmapset(mirror_url, beatmapset_id, on_progress)
+            .await
+    }
+
+    /// Requests per minute shared by all clones.
+    pub fn set_rate_limit(&self, budget: u32) {
+        self.http.rate_limiter.set_budget(budget);
+    }
+
+    pub fn rate_limit_stats(&self) -> RateLimitStats {
+        self.http.rate_limiter.stats()
+    }
+
+    /// Requests and watches of all clones.
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.http.metrics.clone()
+    }
+
+    /// Polls a beatmap until it reaches one of `stop_statuses`, see
+    /// [`Watcher`]. Changes since `previous`, the beatmap as last known, are
+    /// reported on the first poll. Must be called within a Tokio runtime.
+    pub fn watch(
+        &self,
+        beatmap_id: u32,
+        stop_statuses: Vec<RankStatus>,
+        previous: Option<Beatmap>,
+    ) -> Watcher {
+        Watcher::spawn(self.clone(), beatmap_id, stop_statuses, previous)
+    }
+}
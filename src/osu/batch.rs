@@ -0,0 +1,145 @@
+//! Coalesces beatmap lookups from all polling tasks into requests to the
+//! multi-beatmap endpoint.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{self, Instant};
+use tracing::{debug, warn};
+
+use super::api::ApiError;
+use super::http::Http;
+use super::types::Beatmap;
+
+/// Most IDs the endpoint accepts at once.
+const MAX_BATCH: usize = 50;
+/// How long to wait for more lookups after the first one.
+const BATCH_WINDOW: Duration = Duration::from_millis(100);
+
+struct Lookup {
+    http: Http,
+    access_token: String,
+    beatmap_id: u32,
+    /// `None` if the beatmap was not in the batch response, the batch's error
+    /// if it failed.
+    reply: oneshot::Sender<Result<Option<Beatmap>, ApiError>>,
+}
+
+#[derive(Clone)]
+pub struct Batcher {
+    tx: mpsc::UnboundedSender<Lookup>,
+}
+
+impl Batcher {
+    /// Must be called within a Tokio runtime.
+    pub fn spawn() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(run(rx));
+        Self { tx }
+    }
+
+    /// Looks up a beatmap as part of a batch, falling back to its own request
+    /// if it is missing from the batch response. If the batch fails, all its
+    /// lookups fail with the same error instead of retrying one by one.
+    pub async fn get_beatmap(
+        &self,
+        http: &Http,
+        beatmap_id: u32,
+        access_token: &str,
+    ) -> Result<Option<Beatmap>, ApiError> {
+        let (reply, response) = oneshot::channel();
+        let lookup = Lookup {
+            http: http.clone(),
+            access_token: access_token.to_string(),
+            beatmap_id,
+            reply,
+        };
+        if self.tx.send(lookup).is_ok() {
+            match response.await {
+                Ok(Ok(None)) | Err(_) => (),
+                Ok(result) => return result,
+            }
+        }
+        debug!(beatmap_id, "Not in batch, looking up on its own");
+        Ok(http.get_beatmap(beatmap_id, access_token).await?)
+    }
+}
+
+async fn run(mut rx: mpsc::UnboundedReceiver<Lookup>) {
+    while let Some(first) = rx.recv().await {
+        let deadline = Instant::now() + BATCH_WINDOW;
+        let mut lookups = vec![first];
+        while let Ok(Some(lookup)) = time::timeout_at(deadline, rx.recv()).await {
+            lookups.push(lookup);
+        }
+
+        // lookups from before and after switching profiles cannot be combined
+        let mut batches: HashMap<(String, String), Vec<Lookup>> = HashMap::new();
+        for lookup in lookups {
+            batches
+                .entry((
+                    lookup.http.base_url().to_string(),
+                    lookup.access_token.clone(),
+                ))
+                .or_default()
+                .push(lookup);
+        }
+
+        for (_, mut lookups) in batches {
+            while !lookups.is_empty() {
+                let batch = take_batch(&mut lookups);
+                tokio::spawn(fetch(batch));
+            }
+        }
+    }
+}
+
+/// Takes lookups for up to [`MAX_BATCH`] distinct beatmaps.
+fn take_batch(lookups: &mut Vec<Lookup>) -> Vec<Lookup> {
+    let mut beatmap_ids = Vec::new();
+    let mut batch = Vec::new();
+    let mut index = 0;
+    while index < lookups.len() {
+        let beatmap_id = lookups[index].beatmap_id;
+        if beatmap_ids.contains(&beatmap_id) || beatmap_ids.len() < MAX_BATCH {
+            if !beatmap_ids.contains(&beatmap_id) {
+                beatmap_ids.push(beatmap_id);
+            }
+            batch.push(lookups.swap_remove(index));
+        } else {
+            index += 1;
+        }
+    }
+    batch
+}
+
+async fn fetch(lookups: Vec<Lookup>) {
+    let mut beatmap_ids = lookups
+        .iter()
+        .map(|lookup| lookup.beatmap_id)
+        .collect::<Vec<_>>();
+    beatmap_ids.sort_unstable();
+    beatmap_ids.dedup();
+
+    debug!("Looking up {} beatmaps", beatmap_ids.len());
+    let first = &lookups[0];
+    let beatmaps = first
+        .http
+        .get_beatmaps(&beatmap_ids, &first.access_token)
+        .await
+        .map_err(|err| {
+            warn!("Looking up {} beatmaps failed: {err}", beatmap_ids.len());
+            ApiError::from(err)
+        });
+
+    for lookup in lookups {
+        let beatmap = beatmaps.as_ref().map_err(Clone::clone).map(|beatmaps| {
+            beatmaps
+                .iter()
+                .find(|beatmap| beatmap.id == lookup.beatmap_id)
+                .cloned()
+        });
+        lookup.reply.send(beatmap).ok();
+    }
+}
@@ -0,0 +1,98 @@
+//! Reader for the `.osu` beatmap file format.
+//!
+//! See <https://osu.ppy.sh/wiki/en/Client/File_formats/osu_%28file_format%29>.
+
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use zip::ZipArchive;
+
+#[derive(Default)]
+pub struct Metadata {
+    pub title: String,
+    pub artist: String,
+    pub creator: String,
+    pub version: String,
+    /// Missing in beatmaps older than file format v10 and `0` or `-1` in
+    /// unsubmitted ones.
+    pub beatmap_id: Option<u32>,
+    pub beatmapset_id: Option<u32>,
+}
+
+fn parse_id(value: &str) -> Option<u32> {
+    value.parse().ok().filter(|&id| id != 0)
+}
+
+pub fn read_metadata(reader: impl BufRead) -> io::Result<Metadata> {
+    let mut metadata = Metadata::default();
+    let mut in_metadata = false;
+
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim_start_matches('\u{FEFF}').trim();
+        if line.starts_with('[') && line.ends_with(']') {
+            if in_metadata {
+                break;
+            }
+            in_metadata = line == "[Metadata]";
+            continue;
+        }
+        if !in_metadata {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim();
+            match key.trim() {
+                "Title" => metadata.title = value.to_string(),
+                "Artist" => metadata.artist = value.to_string(),
+                "Creator" => metadata.creator = value.to_string(),
+                "Version" => metadata.version = value.to_string(),
+                "BeatmapID" => metadata.beatmap_id = parse_id(value),
+                "BeatmapSetID" => metadata.beatmapset_id = parse_id(value),
+                _ => (),
+            }
+        }
+    }
+
+    Ok(metadata)
+}
+
+fn is_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+}
+
+/// Reads the metadata of every beatmap in a `.osu` file, a beatmapset folder or
+/// a `.osz` archive.
+pub fn read_metadata_from_path(path: &Path) -> io::Result<Vec<Metadata>> {
+    if path.is_dir() {
+        let mut metadata = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let path = entry?.path();
+            if path.is_file() && is_extension(&path, "osu") {
+                metadata.push(read_metadata(BufReader::new(File::open(path)?))?);
+            }
+        }
+        Ok(metadata)
+    } else if is_extension(path, "osz") {
+        let mut archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+        let mut metadata = Vec::new();
+        for index in 0..archive.len() {
+            let file = archive.by_index(index)?;
+            if file.name().to_ascii_lowercase().ends_with(".osu") {
+                metadata.push(read_metadata(BufReader::new(file))?);
+            }
+        }
+        Ok(metadata)
+    } else if is_extension(path, "osu") {
+        Ok(vec![read_metadata(BufReader::new(File::open(path)?))?])
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not a .osu file, .osz archive or beatmap folder",
+        ))
+    }
+}
@@ -0,0 +1,216 @@
+//! Readers for the osu!stable `collection.db` and `osu!.db` files.
+//!
+//! See <https://github.com/ppy/osu/wiki/Legacy-database-file-structure>.
+
+use std::collections::HashMap;
+use std::io::{self, Read};
+
+/// Versions before this one prefix every beatmap entry with its size.
+const ENTRY_SIZE_REMOVED: i32 = 20_191_106;
+/// Versions before this one store difficulty settings as bytes and have no
+/// star rating caches.
+const FLOAT_DIFFICULTY: i32 = 20_140_609;
+
+pub struct Collection {
+    pub name: String,
+    pub beatmap_hashes: Vec<String>,
+}
+
+pub struct DatabaseBeatmap {
+    pub artist: String,
+    pub title: String,
+    pub difficulty: String,
+    pub md5: String,
+    /// `None` for beatmaps which were never submitted.
+    pub beatmap_id: Option<u32>,
+    pub beatmapset_id: Option<u32>,
+}
+
+struct Reader<R> {
+    inner: R,
+}
+
+impl<R: Read> Reader<R> {
+    fn bytes<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut buf = [0; N];
+        self.inner.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn skip(&mut self, count: u64) -> io::Result<()> {
+        let skipped = io::copy(&mut self.inner.by_ref().take(count), &mut io::sink())?;
+        if skipped == count {
+            Ok(())
+        } else {
+            Err(io::ErrorKind::UnexpectedEof.into())
+        }
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes::<1>()?[0])
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        Ok(i32::from_le_bytes(self.bytes()?))
+    }
+
+    fn id(&mut self) -> io::Result<Option<u32>> {
+        Ok(u32::try_from(self.i32()?).ok().filter(|&id| id != 0))
+    }
+
+    fn len(&mut self) -> io::Result<usize> {
+        usize::try_from(self.i32()?).map_err(|_| invalid_data("negative length"))
+    }
+
+    fn uleb128(&mut self) -> io::Result<usize> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            value |= usize::from(byte & 0x7F)
+                .checked_shl(shift)
+                .ok_or_else(|| invalid_data("string length overflow"))?;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        match self.u8()? {
+            0x00 => Ok(String::new()),
+            0x0B => {
+                let len = self.uleb128()?;
+                let mut buf = Vec::new();
+                self.inner.by_ref().take(len as u64).read_to_end(&mut buf)?;
+                if buf.len() != len {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                String::from_utf8(buf).map_err(|_| invalid_data("string is not UTF-8"))
+            }
+            _ => Err(invalid_data("invalid string marker")),
+        }
+    }
+
+    fn star_ratings(&mut self) -> io::Result<()> {
+        for _ in 0..self.len()? {
+            // 0x08, mods, then 0x0D and a double or 0x0C and a single
+            self.skip(5)?;
+            match self.u8()? {
+                0x0C => self.skip(4)?,
+                0x0D => self.skip(8)?,
+                _ => return Err(invalid_data("invalid star rating marker")),
+            }
+        }
+        Ok(())
+    }
+
+    fn beatmap(&mut self, version: i32) -> io::Result<DatabaseBeatmap> {
+        if version < ENTRY_SIZE_REMOVED {
+            self.skip(4)?;
+        }
+        let artist = self.string()?;
+        self.string()?; // artist, in Unicode
+        let title = self.string()?;
+        self.string()?; // title, in Unicode
+        self.string()?; // creator
+        let difficulty = self.string()?;
+        self.string()?; // audio file
+        let md5 = self.string()?;
+        self.string()?; // .osu file
+        self.skip(1 + 3 * 2 + 8)?; // ranked status, object counts and modification time
+        if version < FLOAT_DIFFICULTY {
+            self.skip(4)?; // AR, CS, HP and OD
+        } else {
+            self.skip(4 * 4)?;
+        }
+        self.skip(8)?; // slider velocity
+        if version >= FLOAT_DIFFICULTY {
+            for _ in 0..4 {
+                self.star_ratings()?;
+            }
+        }
+        self.skip(3 * 4)?; // drain time, total time and preview time
+        let timing_points = self.len()?;
+        self.skip(timing_points as u64 * 17)?;
+        let beatmap_id = self.id()?;
+        let beatmapset_id = self.id()?;
+        // thread ID, grades, local offset, stack leniency and mode
+        self.skip(4 + 4 + 2 + 4 + 1)?;
+        self.string()?; // source
+        self.string()?; // tags
+        self.skip(2)?; // online offset
+        self.string()?; // title font
+        self.skip(1 + 8 + 1)?; // unplayed, last played and osz2
+        self.string()?; // folder name
+        self.skip(8)?; // last checked
+        self.skip(5)?; // ignore sound and skin, disable storyboard and video, visual override
+        if version < FLOAT_DIFFICULTY {
+            self.skip(2)?;
+        }
+        self.skip(4 + 1)?; // modification time and mania scroll speed
+
+        Ok(DatabaseBeatmap {
+            artist,
+            title,
+            difficulty,
+            md5,
+            beatmap_id,
+            beatmapset_id,
+        })
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub fn read_collections(reader: impl Read) -> io::Result<Vec<Collection>> {
+    let mut reader = Reader { inner: reader };
+    reader.i32()?; // version
+    (0..reader.len()?)
+        .map(|_| {
+            let name = reader.string()?;
+            let beatmap_hashes = (0..reader.len()?)
+                .map(|_| reader.string())
+                .collect::<io::Result<_>>()?;
+            Ok(Collection {
+                name,
+                beatmap_hashes,
+            })
+        })
+        .collect()
+}
+
+pub fn read_beatmaps(reader: impl Read) -> io::Result<Vec<DatabaseBeatmap>> {
+    let mut reader = Reader { inner: reader };
+    let version = reader.i32()?;
+    // folder count, account unlocked and account unlock date
+    reader.skip(4 + 1 + 8)?;
+    reader.string()?; // player name
+    (0..reader.len()?)
+        .map(|_| reader.beatmap(version))
+        .collect()
+}
+
+/// Maps every hash in `collection` to a beatmap ID, returning the IDs found and
+/// the number of hashes which are missing from `beatmaps` or were never
+/// submitted.
+pub fn resolve_collection(
+    collection: &Collection,
+    beatmaps: &HashMap<&str, &DatabaseBeatmap>,
+) -> (Vec<u32>, usize) {
+    let mut beatmap_ids = Vec::new();
+    let mut missing = 0;
+    for hash in &collection.beatmap_hashes {
+        match beatmaps
+            .get(hash.as_str())
+            .and_then(|beatmap| beatmap.beatmap_id)
+        {
+            Some(beatmap_id) => beatmap_ids.push(beatmap_id),
+            None => missing += 1,
+        }
+    }
+    (beatmap_ids, missing)
+}
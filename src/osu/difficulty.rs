@@ -0,0 +1,177 @@
+//! Star rating and pp of `.osu` files, calculated offline with `rosu-pp`.
+
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use md5::{Digest, Md5};
+use rosu_pp::model::beatmap::TooSuspicious;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+/// Accuracies pp is calculated for, in percent.
+pub const ACCURACIES: [f64; 4] = [100., 99., 98., 95.];
+
+/// Mods changing difficulty, as the bit flags of osu!stable.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug, Serialize, Deserialize)]
+pub struct Mods(u32);
+
+impl Mods {
+    /// With their acronyms, in the order osu! lists them.
+    pub const ALL: [(Self, &'static str); 6] = [
+        (Self::EASY, "EZ"),
+        (Self::HIDDEN, "HD"),
+        (Self::HARD_ROCK, "HR"),
+        (Self::DOUBLE_TIME, "DT"),
+        (Self::HALF_TIME, "HT"),
+        (Self::FLASHLIGHT, "FL"),
+    ];
+    pub const DOUBLE_TIME: Self = Self(1 << 6);
+    pub const EASY: Self = Self(1 << 1);
+    pub const FLASHLIGHT: Self = Self(1 << 10);
+    pub const HALF_TIME: Self = Self(1 << 8);
+    pub const HARD_ROCK: Self = Self(1 << 4);
+    pub const HIDDEN: Self = Self(1 << 3);
+    pub const NONE: Self = Self(0);
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub fn contains(self, mods: Self) -> bool {
+        self.0 & mods.0 == mods.0
+    }
+
+    /// Adds or removes `mods`, removing those which cannot be combined with
+    /// them.
+    #[must_use]
+    pub fn toggle(self, mods: Self) -> Self {
+        if self.contains(mods) {
+            return Self(self.0 & !mods.0);
+        }
+        let conflicting = match mods {
+            Self::EASY => Self::HARD_ROCK,
+            Self::HARD_ROCK => Self::EASY,
+            Self::DOUBLE_TIME => Self::HALF_TIME,
+            Self::HALF_TIME => Self::DOUBLE_TIME,
+            _ => Self::NONE,
+        };
+        Self(self.0 & !conflicting.0 | mods.0)
+    }
+}
+
+impl Display for Mods {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if *self == Self::NONE {
+            return f.write_str("NM");
+        }
+        for (mods, acronym) in Self::ALL {
+            if self.contains(mods) {
+                f.write_str(acronym)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Performance {
+    pub mods: Mods,
+    pub stars: f64,
+    pub max_combo: u32,
+    /// pp of a full combo at each of [`ACCURACIES`].
+    pub pp: [f64; ACCURACIES.len()],
+}
+
+pub enum PerformanceState {
+    Calculating,
+    Calculated(Performance),
+    Failed(String),
+}
+
+#[derive(Debug)]
+pub enum DifficultyError {
+    Io(io::Error),
+    /// Calculating could take very long or use a lot of memory.
+    Suspicious(TooSuspicious),
+}
+
+impl Display for DifficultyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DifficultyError::Io(err) => err.fmt(f),
+            DifficultyError::Suspicious(err) => write!(f, "Not calculated: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for DifficultyError {}
+
+impl From<io::Error> for DifficultyError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<TooSuspicious> for DifficultyError {
+    fn from(err: TooSuspicious) -> Self {
+        Self::Suspicious(err)
+    }
+}
+
+/// Calculates for the mode of the `.osu` file. May take a while for long
+/// beatmaps, so better not run on an async task.
+pub fn calculate(osu_file: &[u8], mods: Mods) -> Result<Performance, DifficultyError> {
+    let map = rosu_pp::Beatmap::from_bytes(osu_file)?;
+    map.check_suspicion()?;
+
+    let difficulty = rosu_pp::Difficulty::new().mods(mods.bits()).calculate(&map);
+    let stars = difficulty.stars();
+    let max_combo = difficulty.max_combo();
+    let mut pp = [0.; ACCURACIES.len()];
+    let mut attributes = difficulty;
+    for (pp, accuracy) in pp.iter_mut().zip(ACCURACIES) {
+        let performance = rosu_pp::Performance::new(attributes)
+            .mods(mods.bits())
+            .accuracy(accuracy)
+            .calculate();
+        *pp = performance.pp();
+        attributes = performance.difficulty_attributes();
+    }
+
+    Ok(Performance {
+        mods,
+        stars,
+        max_combo,
+        pp,
+    })
+}
+
+/// `beatmaps` in the app's cache directory, holding `.osu` files by beatmap
+/// ID.
+pub fn default_cache_dir(app_name: &str) -> Option<PathBuf> {
+    directories_next::ProjectDirs::from("", "", app_name)
+        .map(|dirs| dirs.cache_dir().join("beatmaps"))
+}
+
+/// Reads the cached `.osu` file of a beatmap, unless it is missing or does not
+/// match the MD5 `checksum` of the current version.
+pub fn read_cached(cache_dir: &Path, beatmap_id: u32, checksum: Option<&str>) -> Option<Vec<u8>> {
+    let osu_file = fs::read(cache_path(cache_dir, beatmap_id)).ok()?;
+    match checksum {
+        Some(checksum) if format!("{:x}", Md5::digest(&osu_file)) != checksum => {
+            debug!(beatmap_id, "Cached beatmap is outdated");
+            None
+        }
+        _ => Some(osu_file),
+    }
+}
+
+pub fn write_cached(cache_dir: &Path, beatmap_id: u32, osu_file: &[u8]) -> io::Result<()> {
+    fs::create_dir_all(cache_dir)?;
+    fs::write(cache_path(cache_dir, beatmap_id), osu_file)
+}
+
+fn cache_path(cache_dir: &Path, beatmap_id: u32) -> PathBuf {
+    cache_dir.join(format!("{beatmap_id}.osu"))
+}
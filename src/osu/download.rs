@@ -0,0 +1,187 @@
+use std::fmt::Display;
+use std::fs;
+use std::io::{self, Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use md5::{Digest, Md5};
+use reqwest::StatusCode;
+use tracing::info;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+use super::http::Http;
+use super::types::Beatmapset;
+
+/// Upper bound for preallocating the archive from `Content-Length`.
+const MAX_PREALLOCATION: u64 = 200 * 1024 * 1024;
+/// Progress is reported at most every 1% or this often.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+pub enum DownloadState {
+    Downloading { downloaded: u64, total: Option<u64> },
+    Installing,
+    Installed(PathBuf),
+    Failed(String),
+}
+
+#[derive(Debug)]
+pub enum DownloadError {
+    Http(reqwest::Error),
+    Status(StatusCode),
+    Io(io::Error),
+    Archive(ZipError),
+    ChecksumMismatch,
+}
+
+impl Display for DownloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DownloadError::Http(err) => match err.status() {
+                Some(status) => status.fmt(f),
+                None => f.write_str("Network error"),
+            },
+            DownloadError::Status(status) => status.fmt(f),
+            DownloadError::Io(err) => err.fmt(f),
+            DownloadError::Archive(err) => err.fmt(f),
+            DownloadError::ChecksumMismatch => f.write_str("Checksum mismatch"),
+        }
+    }
+}
+
+impl std::error::Error for DownloadError {}
+
+impl From<reqwest::Error> for DownloadError {
+    fn from(err: reqwest::Error) -> Self {
+        Self::Http(err)
+    }
+}
+
+impl From<io::Error> for DownloadError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ZipError> for DownloadError {
+    fn from(err: ZipError) -> Self {
+        Self::Archive(err)
+    }
+}
+
+impl Http {
+    pub async fn download_beatmapset(
+        &self,
+        mirror_url: impl AsRef<str>,
+        beatmapset_id: u32,
+        mut on_progress: impl FnMut(u64, Option<u64>),
+    ) -> Result<Vec<u8>, DownloadError> {
+        info!(beatmapset_id, "Downloading from {}", mirror_url.as_ref());
+        let mut response = self
+            .send(
+                "download",
+                self.http_client.get(format!(
+                    "{}/{beatmapset_id}",
+                    mirror_url.as_ref().trim_end_matches('/')
+                )),
+            )
+            .await?;
+
+        if response.status() != StatusCode::OK {
+            return Err(DownloadError::Status(response.status()));
+        }
+
+        let total = response.content_length();
+        // the mirror's Content-Length is only a hint, don't trust it with the
+        // allocation
+        let capacity = total.map_or(0, |total| total.min(MAX_PREALLOCATION));
+        let mut archive = Vec::with_capacity(capacity.try_into().unwrap_or(0));
+        on_progress(0, total);
+        let mut reported = (0, Instant::now());
+        while let Some(chunk) = response.chunk().await? {
+            archive.extend_from_slice(&chunk);
+            let downloaded = archive.len() as u64;
+            let step = total.map_or(u64::MAX, |total| total / 100);
+            if downloaded - reported.0 >= step.max(1) || reported.1.elapsed() >= PROGRESS_INTERVAL {
+                on_progress(downloaded, total);
+                reported = (downloaded, Instant::now());
+            }
+        }
+        on_progress(archive.len() as u64, total);
+
+        Ok(archive)
+    }
+}
+
+/// The folder name osu!stable itself uses when importing a `.osz`.
+pub fn folder_name(beatmapset: &Beatmapset) -> String {
+    format!(
+        "{} {} - {}",
+        beatmapset.id, beatmapset.artist, beatmapset.title
+    )
+    .chars()
+    .filter(|char| !matches!(char, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*'))
+    .collect()
+}
+
+/// The folder a beatmapset is already installed in under `songs_path`. Only
+/// the ID prefix is compared, as the game names folders differently at times,
+/// e.g. with the romanised title.
+pub fn installed(songs_path: &Path, beatmapset_id: u32) -> io::Result<Option<PathBuf>> {
+    let prefix = beatmapset_id.to_string();
+    for entry in fs::read_dir(songs_path)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let matches = name
+            .strip_prefix(&prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '));
+        if matches && entry.file_type()?.is_dir() {
+            return Ok(Some(entry.path()));
+        }
+    }
+    Ok(None)
+}
+
+/// Extracts a downloaded `.osz` into `target`, after checking that one of its
+/// `.osu` files matches the checksum reported by the API.
+pub fn install(archive: &[u8], checksum: Option<&str>, target: &Path) -> Result<(), DownloadError> {
+    let mut archive = ZipArchive::new(Cursor::new(archive))?;
+
+    if let Some(checksum) = checksum {
+        let mut matched = false;
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index)?;
+            if !file.name().ends_with(".osu") {
+                continue;
+            }
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents)?;
+            if format!("{:x}", Md5::digest(&contents)) == checksum {
+                matched = true;
+                break;
+            }
+        }
+        if !matched {
+            return Err(DownloadError::ChecksumMismatch);
+        }
+    }
+
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        let path = match file.enclosed_name() {
+            Some(path) => target.join(path),
+            None => continue,
+        };
+        if file.is_dir() {
+            fs::create_dir_all(&path)?;
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            io::copy(&mut file, &mut fs::File::create(&path)?)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,221 @@
+//! An Atom feed of status changes, for feed readers.
+
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use super::history::{History, Observation};
+use super::types::CoverVariant;
+
+/// The most recent status changes kept in the feed.
+const MAX_ENTRIES: usize = 50;
+
+struct Entry<'a> {
+    previous: &'a Observation,
+    current: &'a Observation,
+}
+
+/// `feed.atom` in the app's data directory.
+pub fn default_path(app_name: &str) -> Option<PathBuf> {
+    directories_next::ProjectDirs::from("", "", app_name)
+        .map(|dirs| dirs.data_dir().join("feed.atom"))
+}
+
+/// Renders the status changes in `history`, newest first. Beatmap links point
+/// to the website at `site_url`.
+pub fn atom(history: &History, title: &str, site_url: &str) -> String {
+    let site_url = site_url.trim_end_matches('/');
+    let mut entries = Vec::new();
+    for beatmap_id in history.beatmap_ids() {
+        let mut previous: Option<&Observation> = None;
+        for observation in history
+            .observations()
+            .iter()
+            .filter(|observation| observation.beatmap_id == beatmap_id)
+        {
+            if let Some(previous) =
+                previous.filter(|previous| previous.status != observation.status)
+            {
+                entries.push(Entry {
+                    previous,
+                    current: observation,
+                });
+            }
+            previous = Some(observation);
+        }
+    }
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.current.first_seen));
+    entries.truncate(MAX_ENTRIES);
+
+    let updated = entries.first().map_or(0, |entry| entry.current.first_seen);
+    let mut feed = String::new();
+    writeln!(feed, r#"<?xml version="1.0" encoding="utf-8"?>"#).unwrap();
+    writeln!(feed, r#"<feed xmlns="http://www.w3.org/2005/Atom">"#).unwrap();
+    writeln!(feed, "  <title>{}</title>", escape(title)).unwrap();
+    writeln!(feed, "  <id>urn:osu-beatmap-watcher:status-changes</id>").unwrap();
+    writeln!(feed, "  <updated>{}</updated>", rfc3339(updated)).unwrap();
+    writeln!(feed, "  <author><name>{}</name></author>", escape(title)).unwrap();
+    for entry in entries {
+        write_entry(&mut feed, &entry, site_url);
+    }
+    writeln!(feed, "</feed>").unwrap();
+    feed
+}
+
+fn write_entry(feed: &mut String, entry: &Entry<'_>, site_url: &str) {
+    let current = entry.current;
+    let name = format!("{} - {}", current.artist, current.title);
+    let url = format!("{site_url}/beatmaps/{}", current.beatmap_id);
+    let cover = CoverVariant::Cover.url(current.beatmapset_id, false);
+    let summary = format!(
+        "{name} is now {} (was {})",
+        current.status, entry.previous.status
+    );
+
+    writeln!(feed, "  <entry>").unwrap();
+    writeln!(
+        feed,
+        "    <title>{}</title>",
+        escape(&format!(
+            "{name}: {} → {}",
+            entry.previous.status, current.status
+        ))
+    )
+    .unwrap();
+    writeln!(
+        feed,
+        "    <id>urn:osu-beatmap-watcher:{}:{}</id>",
+        current.beatmap_id, current.first_seen
+    )
+    .unwrap();
+    writeln!(
+        feed,
+        "    <updated>{}</updated>",
+        rfc3339(current.first_seen)
+    )
+    .unwrap();
+    if !current.creator.is_empty() {
+        writeln!(
+            feed,
+            "    <author><name>{}</name></author>",
+            escape(&current.creator)
+        )
+        .unwrap();
+    }
+    writeln!(feed, r#"    <link href="{}"/>"#, escape(&url)).unwrap();
+    writeln!(
+        feed,
+        r#"    <link rel="enclosure" type="image/jpeg" href="{}"/>"#,
+        escape(&cover)
+    )
+    .unwrap();
+    writeln!(feed, "    <summary>{}</summary>", escape(&summary)).unwrap();
+    let content = format!(
+        r#"<p><img src="{}" alt=""/></p><p><a href="{}">{}</a> by {} is now <b>{}</b> (was {}).</p>"#,
+        escape(&cover),
+        escape(&url),
+        escape(&name),
+        escape(&current.creator),
+        current.status,
+        entry.previous.status
+    );
+    writeln!(
+        feed,
+        r#"    <content type="html">{}</content>"#,
+        escape(&content)
+    )
+    .unwrap();
+    writeln!(feed, "  </entry>").unwrap();
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for char in text.chars() {
+        match char {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(char),
+        }
+    }
+    escaped
+}
+
+/// Formats Unix seconds as an RFC 3339 date in UTC.
+fn rfc3339(time: u64) -> String {
+    let days = time / 86400;
+    let seconds = time % 86400;
+
+    // civil date from days since 1970-01-01, see
+    // <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{atom, escape, rfc3339, MAX_ENTRIES};
+    use crate::osu::history::History;
+    use crate::osu::mock;
+    use crate::osu::types::RankStatus;
+
+    #[test]
+    fn formats_dates() {
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(rfc3339(951_868_799), "2000-02-29T23:59:59Z");
+        assert_eq!(rfc3339(1_700_000_000), "2023-11-14T22:13:20Z");
+    }
+
+    #[test]
+    fn escapes_markup() {
+        assert_eq!(escape(r#"&<>"'"#), "&amp;&lt;&gt;&quot;&apos;");
+        assert_eq!(escape("Künstler - Title"), "Künstler - Title");
+    }
+
+    #[test]
+    fn lists_the_newest_status_changes_first() {
+        let mut history = History::default();
+        for beatmap_id in 1..=60 {
+            let time = u64::from(beatmap_id) * 100;
+            let mut beatmap = mock::beatmap(beatmap_id, beatmap_id, RankStatus::Pending);
+            history.record(&beatmap, time);
+            beatmap.ranked = RankStatus::Qualified;
+            history.record(&beatmap, time + 10);
+        }
+        // no change of status
+        history.record(&mock::beatmap(61, 61, RankStatus::Pending), 10_000);
+
+        let feed = atom(&history, "Title", "https://osu.ppy.sh/");
+        let ids = feed
+            .lines()
+            .filter_map(|line| line.strip_prefix("    <id>urn:osu-beatmap-watcher:"))
+            .filter_map(|id| id.strip_suffix("</id>"))
+            .collect::<Vec<_>>();
+        assert_eq!(ids.len(), MAX_ENTRIES);
+        assert_eq!(ids[0], "60:6010");
+        assert_eq!(ids[MAX_ENTRIES - 1], "11:1110");
+        assert!(feed.contains("  <updated>1970-01-01T01:40:10Z</updated>\n"));
+        assert!(feed.contains(r#"<link href="https://osu.ppy.sh/beatmaps/60"/>"#));
+    }
+}
@@ -0,0 +1,360 @@
+//! Status observations of watched beatmaps, kept across sessions so ranking
+//! timelines can be exported.
+
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use super::types::{Beatmap, Beatmapset, GameMode, RankStatus};
+
+/// A span of polls in which a beatmap looked the same.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Observation {
+    pub beatmap_id: u32,
+    pub beatmapset_id: u32,
+    pub artist: String,
+    pub title: String,
+    #[serde(default)]
+    pub creator: String,
+    #[serde(default)]
+    pub mode: Option<GameMode>,
+    pub status: RankStatus,
+    pub nominations: Option<u32>,
+    /// Unix time in seconds.
+    pub first_seen: u64,
+    /// Unix time in seconds.
+    pub last_seen: u64,
+}
+
+/// Time a beatmap spent in one status, as written by [`History::export`].
+#[derive(Serialize)]
+pub struct StatusPeriod {
+    pub beatmap_id: u32,
+    pub beatmapset_id: u32,
+    pub artist: String,
+    pub title: String,
+    /// Empty for the first status seen.
+    pub previous_status: Option<String>,
+    pub status: String,
+    /// Unix time in seconds.
+    pub entered_at: u64,
+    /// Unix time in seconds, empty while the beatmap is still in this status.
+    pub left_at: Option<u64>,
+    /// Until leaving the status, or until the last poll.
+    pub duration_secs: u64,
+}
+
+/// An observation as written by [`History::export`], with the status named.
+#[derive(Serialize)]
+pub struct ExportedObservation {
+    pub beatmap_id: u32,
+    pub beatmapset_id: u32,
+    pub artist: String,
+    pub title: String,
+    pub creator: String,
+    pub mode: Option<GameMode>,
+    pub status: String,
+    /// Empty if the API didn't report nominations.
+    pub nominations: Option<u32>,
+    /// Unix time in seconds.
+    pub first_seen: u64,
+    /// Unix time in seconds.
+    pub last_seen: u64,
+}
+
+/// What [`History::export`] writes a row for.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ExportContent {
+    /// Each status a beatmap was in, with the transition into it.
+    StatusPeriods,
+    /// Each observation as recorded, including nomination changes.
+    Observations,
+}
+
+impl ExportContent {
+    pub const ALL: [Self; 2] = [Self::StatusPeriods, Self::Observations];
+}
+
+impl Display for ExportContent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ExportContent::StatusPeriods => "Status periods",
+            ExportContent::Observations => "All observations",
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub const ALL: [Self; 2] = [Self::Csv, Self::Json];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+impl Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Json => "JSON",
+        })
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("Unknown export format: {s}")),
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct History {
+    observations: Vec<Observation>,
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+impl History {
+    /// `history.json` in the app's data directory.
+    pub fn default_path(app_name: &str) -> Option<PathBuf> {
+        directories_next::ProjectDirs::from("", "", app_name)
+            .map(|dirs| dirs.data_dir().join("history.json"))
+    }
+
+    /// Reads a history file, a missing file being an empty history.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match File::open(path) {
+            Ok(file) => Ok(serde_json::from_reader(BufReader::new(file))?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()
+    }
+
+    pub fn observations(&self) -> &[Observation] {
+        &self.observations
+    }
+
+    /// Beatmaps with any observations, in the order they were first seen.
+    pub fn beatmap_ids(&self) -> Vec<u32> {
+        let mut beatmap_ids = Vec::new();
+        for observation in &self.observations {
+            if !beatmap_ids.contains(&observation.beatmap_id) {
+                beatmap_ids.push(observation.beatmap_id);
+            }
+        }
+        beatmap_ids
+    }
+
+    /// Records a poll result, extending the latest observation if nothing
+    /// changed since.
+    pub fn record(&mut self, beatmap: &Beatmap, time: u64) {
+        let beatmapset = &beatmap.beatmapset;
+        let nominations = beatmapset
+            .nominations_summary
+            .map(|nominations| nominations.current);
+
+        if let Some(latest) = self
+            .observations
+            .iter_mut()
+            .rev()
+            .find(|observation| observation.beatmap_id == beatmap.id)
+        {
+            if latest.status == beatmap.ranked && latest.nominations == nominations {
+                latest.last_seen = time;
+                latest.mode = latest.mode.or(beatmap.mode);
+                return;
+            }
+        }
+
+        self.observations.push(Observation {
+            beatmap_id: beatmap.id,
+            beatmapset_id: beatmapset.id,
+            artist: beatmapset.artist.clone(),
+            title: beatmapset.title.clone(),
+            creator: beatmapset.creator.clone(),
+            mode: beatmap.mode,
+            status: beatmap.ranked,
+            nominations,
+            first_seen: time,
+            last_seen: time,
+        });
+    }
+
+    /// The beatmap as last seen and when, to show until it can be polled.
+    /// Only what the history records is filled in.
+    pub fn last_known(&self, beatmap_id: u32) -> Option<(Beatmap, u64)> {
+        let observation = self
+            .observations
+            .iter()
+            .rev()
+            .find(|observation| observation.beatmap_id == beatmap_id)?;
+        let beatmap = Beatmap {
+            id: observation.beatmap_id,
+            ranked: observation.status,
+            mode: observation.mode,
+            checksum: None,
+            last_updated: None,
+            difficulty_rating: 0.,
+            ar: 0.,
+            accuracy: 0.,
+            cs: 0.,
+            drain: 0.,
+            total_length: 0,
+            count_circles: 0,
+            count_sliders: 0,
+            count_spinners: 0,
+            beatmapset: Beatmapset {
+                id: observation.beatmapset_id,
+                title: observation.title.clone(),
+                artist: observation.artist.clone(),
+                creator: observation.creator.clone(),
+                nominations_summary: None,
+                hype: None,
+                beatmaps: Vec::new(),
+            },
+        };
+        Some((beatmap, observation.last_seen))
+    }
+
+    /// Merges consecutive observations with the same status, e.g. differing
+    /// only in nominations.
+    pub fn status_periods(&self, beatmap_id: u32) -> Vec<StatusPeriod> {
+        let mut periods: Vec<(&Observation, u64)> = Vec::new();
+        for observation in self
+            .observations
+            .iter()
+            .filter(|observation| observation.beatmap_id == beatmap_id)
+        {
+            match periods.last_mut() {
+                Some((first, last_seen)) if first.status == observation.status => {
+                    *last_seen = observation.last_seen;
+                }
+                _ => periods.push((observation, observation.last_seen)),
+            }
+        }
+
+        let mut status_periods = Vec::with_capacity(periods.len());
+        for (index, (first, last_seen)) in periods.iter().enumerate() {
+            let left_at = periods.get(index + 1).map(|(next, _)| next.first_seen);
+            status_periods.push(StatusPeriod {
+                beatmap_id: first.beatmap_id,
+                beatmapset_id: first.beatmapset_id,
+                artist: first.artist.clone(),
+                title: first.title.clone(),
+                previous_status: index
+                    .checked_sub(1)
+                    .map(|previous| periods[previous].0.status.to_string()),
+                status: first.status.to_string(),
+                entered_at: first.first_seen,
+                left_at,
+                // the clock may have gone backwards, or an imported history be out of order
+                duration_secs: left_at
+                    .unwrap_or(*last_seen)
+                    .saturating_sub(first.first_seen),
+            });
+        }
+        status_periods
+    }
+
+    /// The observations of `beatmap_id` as recorded.
+    pub fn exported_observations(&self, beatmap_id: u32) -> Vec<ExportedObservation> {
+        self.observations
+            .iter()
+            .filter(|observation| observation.beatmap_id == beatmap_id)
+            .map(|observation| ExportedObservation {
+                beatmap_id: observation.beatmap_id,
+                beatmapset_id: observation.beatmapset_id,
+                artist: observation.artist.clone(),
+                title: observation.title.clone(),
+                creator: observation.creator.clone(),
+                mode: observation.mode,
+                status: observation.status.to_string(),
+                nominations: observation.nominations,
+                first_seen: observation.first_seen,
+                last_seen: observation.last_seen,
+            })
+            .collect()
+    }
+
+    /// Writes the status periods or observations of `beatmap_ids`, one row
+    /// each.
+    pub fn export<W: Write>(
+        &self,
+        beatmap_ids: &[u32],
+        content: ExportContent,
+        format: ExportFormat,
+        writer: W,
+    ) -> io::Result<()> {
+        match content {
+            ExportContent::StatusPeriods => write_rows(
+                beatmap_ids
+                    .iter()
+                    .flat_map(|beatmap_id| self.status_periods(*beatmap_id)),
+                format,
+                writer,
+            ),
+            ExportContent::Observations => write_rows(
+                beatmap_ids
+                    .iter()
+                    .flat_map(|beatmap_id| self.exported_observations(*beatmap_id)),
+                format,
+                writer,
+            ),
+        }
+    }
+}
+
+fn write_rows<T: Serialize>(
+    rows: impl Iterator<Item = T>,
+    format: ExportFormat,
+    writer: impl Write,
+) -> io::Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(writer);
+            for row in rows {
+                writer.serialize(row)?;
+            }
+            writer.flush()
+        }
+        ExportFormat::Json => {
+            let mut writer = BufWriter::new(writer);
+            serde_json::to_writer_pretty(&mut writer, &rows.collect::<Vec<_>>())?;
+            writeln!(writer)?;
+            writer.flush()
+        }
+    }
+}
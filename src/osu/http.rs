@@ -1,38 +1,132 @@
-use eframe::epaint::ColorImage;
-use image::{EncodableLayout, ImageFormat};
-use reqwest::StatusCode;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use crate::osu::types::{Beatmap, TokenGrantRequest, TokenGrantResponse};
+use image::{EncodableLayout, ImageFormat, RgbaImage};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use tracing::debug;
+
+use super::api::DEFAULT_BASE_URL;
+use super::metrics::Metrics;
+use super::rate_limit::{RateLimiter, DEFAULT_BUDGET};
+use crate::osu::types::{
+    Beatmap, Beatmaps, Beatmapset, CoverVariant, TokenGrantRequest, TokenGrantResponse,
+};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// For everything but beatmapset downloads, which may take a while.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Total size of the cached covers and previews.
+const ASSET_CACHE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Covers and previews by URL, these never change for a beatmapset. The least
+/// recently used are evicted once over [`ASSET_CACHE_SIZE`].
+#[derive(Default)]
+struct AssetCache {
+    assets: HashMap<String, Arc<[u8]>>,
+    /// Least recently used first.
+    order: VecDeque<String>,
+    size: usize,
+}
+
+impl AssetCache {
+    fn get(&mut self, url: &str) -> Option<Arc<[u8]>> {
+        let asset = self.assets.get(url)?.clone();
+        if let Some(index) = self.order.iter().position(|cached| cached == url) {
+            let url = self.order.remove(index).unwrap();
+            self.order.push_back(url);
+        }
+        Some(asset)
+    }
+
+    fn insert(&mut self, url: String, asset: Arc<[u8]>) {
+        if asset.len() > ASSET_CACHE_SIZE || self.assets.contains_key(&url) {
+            return;
+        }
+        self.size += asset.len();
+        while self.size > ASSET_CACHE_SIZE {
+            match self.order.pop_front() {
+                Some(evicted) => {
+                    if let Some(evicted) = self.assets.remove(&evicted) {
+                        self.size -= evicted.len();
+                    }
+                }
+                None => break,
+            }
+        }
+        self.order.push_back(url.clone());
+        self.assets.insert(url, asset);
+    }
+}
 
 #[derive(Clone)]
 pub struct Http {
-    http_client: reqwest::Client,
+    pub(super) http_client: reqwest::Client,
+    base_url: Arc<str>,
+    /// Shared by all clones, so all polling tasks share one budget.
+    pub(super) rate_limiter: Arc<RateLimiter>,
+    pub(super) metrics: Arc<Metrics>,
+    assets: Arc<Mutex<AssetCache>>,
 }
 
 impl Http {
     pub fn new() -> Self {
         Self {
-            http_client: reqwest::Client::new(),
+            http_client: reqwest::Client::builder()
+                .connect_timeout(CONNECT_TIMEOUT)
+                .build()
+                .unwrap(),
+            base_url: DEFAULT_BASE_URL.into(),
+            rate_limiter: Arc::new(RateLimiter::new(DEFAULT_BUDGET)),
+            metrics: Arc::default(),
+            assets: Arc::default(),
         }
     }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Only affects requests made through this instance and later clones.
+    pub fn set_base_url(&mut self, base_url: &str) {
+        self.base_url = base_url.trim_end_matches('/').into();
+    }
+
+    /// Sends a request, recording its status and latency under `endpoint`.
+    pub(super) async fn send(
+        &self,
+        endpoint: &'static str,
+        request: RequestBuilder,
+    ) -> Result<Response, reqwest::Error> {
+        let start = Instant::now();
+        let response = request.send().await;
+        self.metrics.record_request(
+            endpoint,
+            response.as_ref().ok().map(Response::status),
+            start.elapsed(),
+        );
+        response
+    }
 }
 
 impl Http {
-    const BASE_URL: &str = "https://osu.ppy.sh";
-
     pub async fn get_access_token<S: Into<String>>(
         &self,
         client_id: S,
         client_secret: S,
-    ) -> Result<String, reqwest::Error> {
+    ) -> Result<TokenGrantResponse, reqwest::Error> {
+        self.rate_limiter.acquire().await;
         let response = self
-            .http_client
-            .post(format!("{}/oauth/token", Self::BASE_URL))
-            .json(&TokenGrantRequest::with_credentials(
-                client_id,
-                client_secret,
-            ))
-            .send()
+            .send(
+                "oauth_token",
+                self.http_client
+                    .post(format!("{}/oauth/token", self.base_url))
+                    .json(&TokenGrantRequest::with_credentials(
+                        client_id,
+                        client_secret,
+                    ))
+                    .timeout(REQUEST_TIMEOUT),
+            )
             .await?;
 
         let data = response
@@ -40,7 +134,7 @@ impl Http {
             .json::<TokenGrantResponse>()
             .await?;
 
-        Ok(data.access_token)
+        Ok(data)
     }
 
     pub async fn get_beatmap(
@@ -48,50 +142,160 @@ impl Http {
         beatmap_id: u32,
         access_token: impl AsRef<str>,
     ) -> Result<Option<Beatmap>, reqwest::Error> {
+        self.rate_limiter.acquire().await;
         let response = self
-            .http_client
-            .get(format!("{}/api/v2/beatmaps/{beatmap_id}", Self::BASE_URL))
-            .header("Authorization", format!("Bearer {}", access_token.as_ref()))
-            .send()
+            .send(
+                "beatmap",
+                self.http_client
+                    .get(format!("{}/api/v2/beatmaps/{beatmap_id}", self.base_url))
+                    .header("Authorization", format!("Bearer {}", access_token.as_ref()))
+                    .timeout(REQUEST_TIMEOUT),
+            )
             .await?;
 
-        if response.status() != StatusCode::OK {
+        if response.status() == StatusCode::NOT_FOUND {
+            debug!(beatmap_id, "No beatmap");
             return Ok(None);
         }
 
-        Ok(Some(response.json::<Beatmap>().await?))
+        Ok(Some(response.error_for_status()?.json::<Beatmap>().await?))
     }
 
-    pub async fn get_beatmap_cover(
+    /// Looks up many beatmaps at once, leaving out those which do not exist.
+    pub async fn get_beatmaps(
+        &self,
+        beatmap_ids: &[u32],
+        access_token: impl AsRef<str>,
+    ) -> Result<Vec<Beatmap>, reqwest::Error> {
+        self.rate_limiter.acquire().await;
+        let response = self
+            .send(
+                "beatmaps",
+                self.http_client
+                    .get(format!("{}/api/v2/beatmaps", self.base_url))
+                    .query(
+                        &beatmap_ids
+                            .iter()
+                            .map(|beatmap_id| ("ids[]", beatmap_id))
+                            .collect::<Vec<_>>(),
+                    )
+                    .header("Authorization", format!("Bearer {}", access_token.as_ref()))
+                    .timeout(REQUEST_TIMEOUT),
+            )
+            .await?;
+
+        Ok(response
+            .error_for_status()?
+            .json::<Beatmaps>()
+            .await?
+            .beatmaps)
+    }
+
+    pub async fn get_beatmapset(
+        &self,
+        beatmapset_id: u32,
+        access_token: impl AsRef<str>,
+    ) -> Result<Option<Beatmapset>, reqwest::Error> {
+        self.rate_limiter.acquire().await;
+        let response = self
+            .send(
+                "beatmapset",
+                self.http_client
+                    .get(format!(
+                        "{}/api/v2/beatmapsets/{beatmapset_id}",
+                        self.base_url
+                    ))
+                    .header("Authorization", format!("Bearer {}", access_token.as_ref()))
+                    .timeout(REQUEST_TIMEOUT),
+            )
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            debug!(beatmapset_id, "No beatmapset");
+            return Ok(None);
+        }
+
+        Ok(Some(
+            response.error_for_status()?.json::<Beatmapset>().await?,
+        ))
+    }
+
+    /// `None` if there is no such beatmap, for which the website answers with
+    /// an empty file.
+    pub async fn get_beatmap_file(
         &self,
         beatmap_id: u32,
-    ) -> Result<Option<ColorImage>, reqwest::Error> {
+    ) -> Result<Option<Vec<u8>>, reqwest::Error> {
+        self.rate_limiter.acquire().await;
         let response = self
-            .http_client
-            .get(format!(
-                "https://assets.ppy.sh/beatmaps/{beatmap_id}/covers/list.jpg"
-            ))
-            .send()
+            .send(
+                "osu_file",
+                self.http_client
+                    .get(format!("{}/osu/{beatmap_id}", self.base_url()))
+                    .timeout(REQUEST_TIMEOUT),
+            )
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            debug!(beatmap_id, "No beatmap file");
+            return Ok(None);
+        }
+
+        let osu_file = response.error_for_status()?.bytes().await?;
+        Ok((!osu_file.is_empty()).then(|| osu_file.to_vec()))
+    }
+
+    async fn get_asset(&self, url: String) -> Result<Option<Arc<[u8]>>, reqwest::Error> {
+        if let Some(asset) = self.assets.lock().unwrap().get(&url) {
+            return Ok(Some(asset));
+        }
+
+        debug!(url, "Fetching asset");
+        let response = self
+            .send("asset", self.http_client.get(&url).timeout(REQUEST_TIMEOUT))
             .await?;
 
         if response.status() != StatusCode::OK {
+            debug!(url, status = %response.status(), "No asset");
             return Ok(None);
         }
 
-        let cover = image::load_from_memory_with_format(
-            response.bytes().await?.as_bytes(),
-            ImageFormat::Jpeg,
-        )
-        .unwrap();
-
-        let cover = ColorImage::from_rgba_unmultiplied(
-            [
-                cover.width().try_into().unwrap(),
-                cover.height().try_into().unwrap(),
-            ],
-            cover.into_rgba8().as_bytes(),
-        );
+        let asset = Arc::<[u8]>::from(response.bytes().await?.as_bytes());
+        self.assets.lock().unwrap().insert(url, asset.clone());
+
+        Ok(Some(asset))
+    }
 
-        Ok(Some(cover))
+    pub async fn get_beatmap_cover(
+        &self,
+        beatmapset_id: u32,
+        variant: CoverVariant,
+        high_dpi: bool,
+    ) -> Result<Option<RgbaImage>, reqwest::Error> {
+        let mut cover = self.get_asset(variant.url(beatmapset_id, high_dpi)).await?;
+        // older beatmapsets have no @2x covers
+        if cover.is_none() && high_dpi {
+            cover = self.get_asset(variant.url(beatmapset_id, false)).await?;
+        }
+        let cover = match cover {
+            Some(cover) => cover,
+            None => return Ok(None),
+        };
+
+        match image::load_from_memory_with_format(&cover, ImageFormat::Jpeg) {
+            Ok(cover) => Ok(Some(cover.into_rgba8())),
+            Err(err) => {
+                debug!(beatmapset_id, "Invalid cover: {err}");
+                Ok(None)
+            }
+        }
+    }
+
+    pub async fn get_beatmap_preview(
+        &self,
+        beatmapset_id: u32,
+    ) -> Result<Option<Arc<[u8]>>, reqwest::Error> {
+        self.get_asset(format!("https://b.ppy.sh/preview/{beatmapset_id}.mp3"))
+            .await
     }
 }
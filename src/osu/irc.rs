@@ -0,0 +1,302 @@
+//! Announcing messages over IRC, e.g. to an osu! account through Bancho at
+//! `irc.ppy.sh`.
+
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, watch};
+use tracing::{debug, error, info, warn};
+
+use super::api::Backoff;
+use super::rate_limit::RateLimiter;
+
+/// Bancho drops users sending much more than this.
+const MESSAGES_PER_MINUTE: u32 = 30;
+/// How long a dropped [`Notifier`] may take to say goodbye.
+const QUIT_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IrcConfig {
+    /// `host:port`
+    pub server: String,
+    pub nick: String,
+    /// The IRC password from <https://osu.ppy.sh/home/account/edit#legacy-api>
+    /// for Bancho, may be empty elsewhere.
+    pub password: String,
+    /// A nick, or a channel starting with `#` which is joined first.
+    pub target: String,
+}
+
+impl Default for IrcConfig {
+    fn default() -> Self {
+        Self {
+            server: "irc.ppy.sh:6667".to_string(),
+            nick: String::new(),
+            password: String::new(),
+            target: String::new(),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum IrcStatus {
+    Connecting,
+    /// The server accepted the nick and messages are being sent.
+    Connected,
+    /// The server refused the password or the nick, with its reason. Retrying
+    /// would not help, so the notifier stopped.
+    Refused(String),
+}
+
+/// Sends messages to the target of an [`IrcConfig`], connecting in the
+/// background and reconnecting whenever the connection is lost. Messages wait
+/// while disconnected. A refused password or nick stops it, see
+/// [`IrcStatus::Refused`]. Dropping it sends `QUIT` if connected, dropping
+/// messages not sent yet.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use osu_beatmap_watcher::osu::irc::{IrcConfig, Notifier};
+/// use osu_beatmap_watcher::osu::mock::MockIrcServer;
+///
+/// let server = MockIrcServer::start().unwrap();
+/// let config = IrcConfig {
+///     server: server.address().to_string(),
+///     nick: "watcher".to_string(),
+///     password: String::new(),
+///     target: "#osu".to_string(),
+/// };
+///
+/// let rt = tokio::runtime::Runtime::new().unwrap();
+/// let notifier = rt.block_on(async { Notifier::spawn(config) });
+/// notifier.send("Qualified → Ranked");
+///
+/// for _ in 0..50 {
+///     if server
+///         .received()
+///         .contains(&"PRIVMSG #osu :Qualified → Ranked".to_string())
+///     {
+///         break;
+///     }
+///     std::thread::sleep(Duration::from_millis(100));
+/// }
+/// assert!(server.received().contains(&"JOIN #osu".to_string()));
+/// assert!(server
+///     .received()
+///     .contains(&"PRIVMSG #osu :Qualified → Ranked".to_string()));
+/// ```
+pub struct Notifier {
+    tx: mpsc::UnboundedSender<String>,
+    status: Arc<Mutex<IrcStatus>>,
+    /// Closed when dropped, telling the task to quit.
+    _quit: watch::Sender<()>,
+}
+
+impl Notifier {
+    /// Must be called within a Tokio runtime.
+    pub fn spawn(config: IrcConfig) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let (quit, quit_rx) = watch::channel(());
+        let status = Arc::new(Mutex::new(IrcStatus::Connecting));
+        tokio::spawn(run(config, rx, quit_rx, status.clone()));
+        Self {
+            tx,
+            status,
+            _quit: quit,
+        }
+    }
+
+    /// Queues a message, line breaks are replaced with spaces.
+    pub fn send(&self, message: impl Into<String>) {
+        self.tx.send(message.into().replace(['\r', '\n'], " ")).ok();
+    }
+
+    pub fn status(&self) -> IrcStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Whether the server accepted the nick and messages are being sent.
+    pub fn is_connected(&self) -> bool {
+        self.status() == IrcStatus::Connected
+    }
+}
+
+/// How a session ended without a connection error.
+enum SessionEnd {
+    /// The [`Notifier`] was dropped.
+    Quit,
+    /// The server refused the password or the nick.
+    Refused(String),
+}
+
+async fn run(
+    config: IrcConfig,
+    mut rx: mpsc::UnboundedReceiver<String>,
+    mut quit: watch::Receiver<()>,
+    status: Arc<Mutex<IrcStatus>>,
+) {
+    let limiter = RateLimiter::new(MESSAGES_PER_MINUTE);
+    let mut backoff = Backoff::new();
+    // taken from the queue but not sent yet
+    let mut pending = None;
+    let mut registered_before = false;
+    loop {
+        let mut session_quit = quit.clone();
+        let result = tokio::select! {
+            result = session(
+                &config,
+                &mut rx,
+                &mut session_quit,
+                &mut pending,
+                &limiter,
+                &mut backoff,
+                &status,
+                &mut registered_before,
+            ) => result,
+            // the session sends QUIT itself, but may be stuck connecting
+            () = async {
+                quit_requested(&mut quit).await;
+                tokio::time::sleep(QUIT_TIMEOUT).await;
+            } => Ok(SessionEnd::Quit),
+        };
+        *status.lock().unwrap() = IrcStatus::Connecting;
+        match result {
+            Ok(SessionEnd::Quit) => break,
+            Ok(SessionEnd::Refused(reason)) => {
+                error!(server = config.server, "IRC login refused: {reason}");
+                *status.lock().unwrap() = IrcStatus::Refused(reason);
+                break;
+            }
+            Err(err) => {
+                let retry_in = backoff.next_delay();
+                warn!(
+                    server = config.server,
+                    "IRC connection failed, retrying in {}s: {err}",
+                    retry_in.as_secs()
+                );
+                tokio::select! {
+                    () = tokio::time::sleep(retry_in) => (),
+                    () = quit_requested(&mut quit) => break,
+                }
+            }
+        }
+    }
+}
+
+/// Connects and sends messages until the connection fails, the login is
+/// refused or the [`Notifier`] is dropped.
+#[allow(clippy::too_many_arguments)]
+async fn session(
+    config: &IrcConfig,
+    rx: &mut mpsc::UnboundedReceiver<String>,
+    quit: &mut watch::Receiver<()>,
+    pending: &mut Option<String>,
+    limiter: &RateLimiter,
+    backoff: &mut Backoff,
+    status: &Mutex<IrcStatus>,
+    registered_before: &mut bool,
+) -> io::Result<SessionEnd> {
+    let stream = TcpStream::connect(&config.server).await?;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    if !config.password.is_empty() {
+        write_line(&mut writer, &format!("PASS {}", config.password)).await?;
+    }
+    write_line(&mut writer, &format!("NICK {}", config.nick)).await?;
+    write_line(&mut writer, &format!("USER {0} 0 * :{0}", config.nick)).await?;
+
+    let mut registered = false;
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let line = line?.ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::UnexpectedEof, "Connection closed")
+                })?;
+                debug!(server = config.server, "{line}");
+                if let Some(token) = line.strip_prefix("PING ") {
+                    write_line(&mut writer, &format!("PONG {token}")).await?;
+                    continue;
+                }
+                match line.split_whitespace().nth(1).unwrap_or_default() {
+                    // welcome
+                    "001" => {
+                        info!(server = config.server, "Connected to IRC");
+                        registered = true;
+                        *registered_before = true;
+                        *backoff = Backoff::new();
+                        *status.lock().unwrap() = IrcStatus::Connected;
+                        if config.target.starts_with('#') {
+                            write_line(&mut writer, &format!("JOIN {}", config.target)).await?;
+                        }
+                    }
+                    // bad password
+                    "464" => return Ok(SessionEnd::Refused(reason(&line))),
+                    // nick in use, possibly by the previous connection if it
+                    // was not closed cleanly
+                    "433" if *registered_before => {
+                        return Err(io::Error::new(io::ErrorKind::AddrInUse, line));
+                    }
+                    "433" => return Ok(SessionEnd::Refused(reason(&line))),
+                    _ => (),
+                }
+            }
+            () = quit_requested(quit) => {
+                tokio::time::timeout(QUIT_TIMEOUT, write_line(&mut writer, "QUIT")).await.ok();
+                return Ok(SessionEnd::Quit);
+            }
+            message = next_message(rx, pending, limiter), if registered => {
+                let message = match message {
+                    Some(message) => message,
+                    None => {
+                        write_line(&mut writer, "QUIT").await.ok();
+                        return Ok(SessionEnd::Quit);
+                    }
+                };
+                write_line(&mut writer, &format!("PRIVMSG {} :{message}", config.target)).await?;
+                *pending = None;
+            }
+        }
+    }
+}
+
+/// The trailing text of a reply, e.g. `Bad authentication token.` from
+/// `:cho.ppy.sh 464 nick :Bad authentication token.`.
+fn reason(line: &str) -> String {
+    line.trim_start_matches(':')
+        .split_once(" :")
+        .map_or(line, |(_, reason)| reason)
+        .to_string()
+}
+
+/// Resolves once the [`Notifier`] is dropped.
+async fn quit_requested(quit: &mut watch::Receiver<()>) {
+    while quit.changed().await.is_ok() {}
+}
+
+/// Waits for a message and for the rate limit to allow sending it, while the
+/// connection keeps being read. Until it is sent, the message is kept in
+/// `pending` to be sent again after reconnecting.
+async fn next_message(
+    rx: &mut mpsc::UnboundedReceiver<String>,
+    pending: &mut Option<String>,
+    limiter: &RateLimiter,
+) -> Option<String> {
+    let message = match pending {
+        Some(message) => message.clone(),
+        None => pending.insert(rx.recv().await?).clone(),
+    };
+    limiter.acquire().await;
+    Some(message)
+}
+
+async fn write_line(writer: &mut OwnedWriteHalf, line: &str) -> io::Result<()> {
+    writer.write_all(format!("{line}\r\n").as_bytes()).await
+}
@@ -0,0 +1,154 @@
+//! Beatmap links as copied from the website or the game.
+
+/// What a link points to. Links to a difficulty usually name its beatmapset
+/// too, plain IDs are taken as beatmap IDs.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BeatmapLink {
+    pub beatmap_id: Option<u32>,
+    pub beatmapset_id: Option<u32>,
+}
+
+const HOSTS: [&str; 3] = ["osu.ppy.sh", "old.ppy.sh", "new.ppy.sh"];
+
+/// Reads a beatmap ID or a link like
+/// - `https://osu.ppy.sh/beatmapsets/1#osu/75`
+/// - `https://osu.ppy.sh/beatmapsets/1/discussion/75/general`
+/// - `https://osu.ppy.sh/beatmaps/75` or `https://osu.ppy.sh/b/75`
+/// - `https://osu.ppy.sh/beatmapsets/1` or `https://osu.ppy.sh/s/1`
+/// - `osu://b/75`, `osu://s/1` or `osu://dl/1`
+///
+/// Surrounding whitespace is ignored, any other text is not.
+pub fn parse(text: &str) -> Option<BeatmapLink> {
+    let text = text.trim();
+    if let Ok(beatmap_id) = text.parse() {
+        return Some(BeatmapLink {
+            beatmap_id: Some(beatmap_id),
+            beatmapset_id: None,
+        });
+    }
+
+    let path = if let Some(path) = text.strip_prefix("osu://") {
+        path
+    } else {
+        let url = text
+            .strip_prefix("https://")
+            .or_else(|| text.strip_prefix("http://"))?;
+        let (host, path) = url.split_once('/')?;
+        if !HOSTS.contains(&host) {
+            return None;
+        }
+        path
+    };
+    let (path, fragment) = path.split_once('#').unwrap_or((path, ""));
+    let path = path.split_once('?').map_or(path, |(path, _)| path);
+    let segments = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    let id = |segment: Option<&&str>| segment.and_then(|segment| segment.parse::<u32>().ok());
+
+    let (beatmap_id, beatmapset_id) = match segments.first().copied() {
+        Some("beatmapsets" | "s" | "dl") => {
+            let beatmapset_id = id(segments.get(1));
+            // `#osu/75`, or a difficulty's discussion
+            let beatmap_id = fragment
+                .split_once('/')
+                .and_then(|(_, beatmap_id)| beatmap_id.parse().ok())
+                .or_else(|| {
+                    (segments.get(2) == Some(&"discussion"))
+                        .then(|| id(segments.get(3)))
+                        .flatten()
+                });
+            (beatmap_id, beatmapset_id)
+        }
+        Some("beatmaps" | "b") => (id(segments.get(1)), None),
+        _ => (None, None),
+    };
+    (beatmap_id.is_some() || beatmapset_id.is_some()).then_some(BeatmapLink {
+        beatmap_id,
+        beatmapset_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(beatmap_id: Option<u32>, beatmapset_id: Option<u32>) -> Option<BeatmapLink> {
+        Some(BeatmapLink {
+            beatmap_id,
+            beatmapset_id,
+        })
+    }
+
+    #[test]
+    fn parses_plain_ids() {
+        assert_eq!(parse("75"), link(Some(75), None));
+        assert_eq!(parse("  75\n"), link(Some(75), None));
+    }
+
+    #[test]
+    fn parses_website_links() {
+        assert_eq!(
+            parse("https://osu.ppy.sh/beatmapsets/1#osu/75"),
+            link(Some(75), Some(1))
+        );
+        assert_eq!(
+            parse("https://osu.ppy.sh/beatmapsets/1/discussion/75/general"),
+            link(Some(75), Some(1))
+        );
+        assert_eq!(
+            parse("https://osu.ppy.sh/beatmapsets/1/discussion/-/generalAll"),
+            link(None, Some(1))
+        );
+        assert_eq!(
+            parse("https://osu.ppy.sh/beatmaps/75"),
+            link(Some(75), None)
+        );
+        assert_eq!(parse("https://osu.ppy.sh/b/75"), link(Some(75), None));
+        assert_eq!(
+            parse("https://osu.ppy.sh/beatmapsets/1"),
+            link(None, Some(1))
+        );
+        assert_eq!(parse("https://osu.ppy.sh/s/1"), link(None, Some(1)));
+    }
+
+    #[test]
+    fn parses_other_hosts_schemes_and_queries() {
+        assert_eq!(parse("http://old.ppy.sh/b/75"), link(Some(75), None));
+        assert_eq!(
+            parse("https://new.ppy.sh/beatmaps/75?mode=osu"),
+            link(Some(75), None)
+        );
+        assert_eq!(
+            parse("https://osu.ppy.sh/beatmapsets/1/?q=x#taiko/75"),
+            link(Some(75), Some(1))
+        );
+    }
+
+    #[test]
+    fn parses_game_links() {
+        assert_eq!(parse("osu://b/75"), link(Some(75), None));
+        assert_eq!(parse("osu://s/1"), link(None, Some(1)));
+        assert_eq!(parse("osu://dl/1"), link(None, Some(1)));
+    }
+
+    #[test]
+    fn rejects_foreign_hosts() {
+        assert_eq!(parse("https://example.com/beatmapsets/1#osu/75"), None);
+        assert_eq!(parse("https://osu.ppy.sh.example.com/b/75"), None);
+        assert_eq!(parse("ftp://osu.ppy.sh/b/75"), None);
+    }
+
+    #[test]
+    fn rejects_other_text() {
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("hello"), None);
+        assert_eq!(parse("-75"), None);
+        assert_eq!(parse("map 75"), None);
+        assert_eq!(parse("see https://osu.ppy.sh/b/75"), None);
+        assert_eq!(parse("https://osu.ppy.sh/users/2"), None);
+        assert_eq!(parse("https://osu.ppy.sh/b/abc"), None);
+        assert_eq!(parse("https://osu.ppy.sh"), None);
+    }
+}
@@ -0,0 +1,230 @@
+//! Counters and gauges about API requests and watches, rendered in the
+//! Prometheus text format.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use reqwest::StatusCode;
+
+use super::types::RankStatus;
+
+/// Upper bounds of the request latency buckets, in seconds.
+const LATENCY_BUCKETS: [f64; 9] = [0.05, 0.1, 0.25, 0.5, 1., 2.5, 5., 10., 30.];
+
+#[derive(Default)]
+struct Histogram {
+    /// Not cumulative, one count per bucket and a last one for the rest.
+    buckets: [u64; LATENCY_BUCKETS.len() + 1],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[bucket] += 1;
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+struct WatchedBeatmap {
+    status: RankStatus,
+    /// Unix time in seconds.
+    last_poll: u64,
+}
+
+#[derive(Default)]
+struct Data {
+    /// By endpoint and status code, `None` if there was no response.
+    requests: BTreeMap<(&'static str, Option<u16>), u64>,
+    latency: BTreeMap<&'static str, Histogram>,
+    /// Not counting logging in.
+    token_refreshes: u64,
+    active_watches: u64,
+    beatmaps: BTreeMap<u32, WatchedBeatmap>,
+    /// Unix time in seconds.
+    last_poll: Option<u64>,
+}
+
+/// Shared by all clones of an [`super::OsuApi`].
+#[derive(Default)]
+pub struct Metrics(Mutex<Data>);
+
+impl Metrics {
+    /// `endpoint` names the kind of request, not its URL, to keep the number of
+    /// series bounded.
+    pub fn record_request(
+        &self,
+        endpoint: &'static str,
+        status: Option<StatusCode>,
+        latency: Duration,
+    ) {
+        let mut data = self.0.lock().unwrap();
+        *data
+            .requests
+            .entry((endpoint, status.map(|status| status.as_u16())))
+            .or_default() += 1;
+        data.latency
+            .entry(endpoint)
+            .or_default()
+            .observe(latency.as_secs_f64());
+    }
+
+    pub fn record_token_refresh(&self) {
+        self.0.lock().unwrap().token_refreshes += 1;
+    }
+
+    pub fn watch_started(&self) {
+        self.0.lock().unwrap().active_watches += 1;
+    }
+
+    pub fn watch_stopped(&self) {
+        let mut data = self.0.lock().unwrap();
+        data.active_watches = data.active_watches.saturating_sub(1);
+    }
+
+    /// Records a successful poll at `time`, in Unix seconds.
+    pub fn record_poll(&self, beatmap_id: u32, status: RankStatus, time: u64) {
+        let mut data = self.0.lock().unwrap();
+        data.beatmaps.insert(
+            beatmap_id,
+            WatchedBeatmap {
+                status,
+                last_poll: time,
+            },
+        );
+        data.last_poll = Some(time);
+    }
+
+    /// Drops the series of a beatmap which is not watched anymore.
+    pub fn remove_beatmap(&self, beatmap_id: u32) {
+        self.0.lock().unwrap().beatmaps.remove(&beatmap_id);
+    }
+
+    pub fn render(&self) -> String {
+        let data = self.0.lock().unwrap();
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "osu_api_requests_total",
+            "counter",
+            "API requests by endpoint and response status, \"error\" if there was no response.",
+        );
+        for ((endpoint, status), count) in &data.requests {
+            let status = status.map_or_else(|| "error".to_string(), |status| status.to_string());
+            writeln!(
+                out,
+                "osu_api_requests_total{{endpoint=\"{endpoint}\",status=\"{status}\"}} {count}"
+            )
+            .unwrap();
+        }
+
+        header(
+            &mut out,
+            "osu_api_request_duration_seconds",
+            "histogram",
+            "API request latency by endpoint.",
+        );
+        for (endpoint, histogram) in &data.latency {
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                cumulative += count;
+                writeln!(
+                    out,
+                    "osu_api_request_duration_seconds_bucket{{endpoint=\"{endpoint}\",le=\"{bound}\"}} {cumulative}"
+                )
+                .unwrap();
+            }
+            writeln!(
+                out,
+                "osu_api_request_duration_seconds_bucket{{endpoint=\"{endpoint}\",le=\"+Inf\"}} {}",
+                histogram.count
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "osu_api_request_duration_seconds_sum{{endpoint=\"{endpoint}\"}} {}",
+                histogram.sum
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "osu_api_request_duration_seconds_count{{endpoint=\"{endpoint}\"}} {}",
+                histogram.count
+            )
+            .unwrap();
+        }
+
+        header(
+            &mut out,
+            "osu_token_refreshes_total",
+            "counter",
+            "Access tokens renewed after expiring or being rejected.",
+        );
+        writeln!(out, "osu_token_refreshes_total {}", data.token_refreshes).unwrap();
+
+        header(
+            &mut out,
+            "osu_active_watches",
+            "gauge",
+            "Beatmaps being polled.",
+        );
+        writeln!(out, "osu_active_watches {}", data.active_watches).unwrap();
+
+        header(
+            &mut out,
+            "osu_beatmap_status",
+            "gauge",
+            "1 for the current status of each watched beatmap, 0 for the others.",
+        );
+        for (beatmap_id, beatmap) in &data.beatmaps {
+            for status in RankStatus::ALL {
+                writeln!(
+                    out,
+                    "osu_beatmap_status{{beatmap_id=\"{beatmap_id}\",status=\"{status}\"}} {}",
+                    u8::from(status == beatmap.status)
+                )
+                .unwrap();
+            }
+        }
+
+        header(
+            &mut out,
+            "osu_beatmap_last_poll_timestamp_seconds",
+            "gauge",
+            "Unix time of the last successful poll of each watched beatmap.",
+        );
+        for (beatmap_id, beatmap) in &data.beatmaps {
+            writeln!(
+                out,
+                "osu_beatmap_last_poll_timestamp_seconds{{beatmap_id=\"{beatmap_id}\"}} {}",
+                beatmap.last_poll
+            )
+            .unwrap();
+        }
+
+        if let Some(last_poll) = data.last_poll {
+            header(
+                &mut out,
+                "osu_last_poll_timestamp_seconds",
+                "gauge",
+                "Unix time of the last successful poll of any beatmap.",
+            );
+            writeln!(out, "osu_last_poll_timestamp_seconds {last_poll}").unwrap();
+        }
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# HELP {name} {help}").unwrap();
+    writeln!(out, "# TYPE {name} {kind}").unwrap();
+}
@@ -0,0 +1,338 @@
+//! Minimal stand-ins for the osu! API and IRC, for examples and tests of tools
+//! built on this crate, behind the `mock` feature. They accept any credentials
+//! unless told otherwise.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use serde_json::json;
+
+use super::types::{Beatmap, Beatmapset, BeatmapsetBeatmap, GameMode, RankStatus};
+
+#[derive(Default)]
+struct MockState {
+    beatmaps: HashMap<u32, Beatmap>,
+    requests: usize,
+    /// Increased to reject the access tokens issued so far.
+    token_generation: u32,
+}
+
+/// Serves the beatmaps it was given on a free local port until dropped.
+pub struct MockServer {
+    address: SocketAddr,
+    url: String,
+    state: Arc<Mutex<MockState>>,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl MockServer {
+    pub fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let address = listener.local_addr()?;
+        let state = Arc::<Mutex<MockState>>::default();
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let server_state = state.clone();
+        let server_shutdown = shutdown.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if server_shutdown.load(Ordering::Relaxed) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let state = server_state.clone();
+                    thread::spawn(move || handle(stream, &state).ok());
+                }
+            }
+        });
+
+        Ok(Self {
+            address,
+            url: format!("http://{address}"),
+            state,
+            shutdown,
+        })
+    }
+
+    /// The base URL to use with [`super::OsuApi::new`].
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Adds or replaces a beatmap.
+    pub fn set_beatmap(&self, beatmap: Beatmap) {
+        self.state
+            .lock()
+            .unwrap()
+            .beatmaps
+            .insert(beatmap.id, beatmap);
+    }
+
+    pub fn set_status(&self, beatmap_id: u32, status: RankStatus) {
+        if let Some(beatmap) = self.state.lock().unwrap().beatmaps.get_mut(&beatmap_id) {
+            beatmap.ranked = status;
+        }
+    }
+
+    /// Rejects the access tokens issued so far, as if they expired.
+    pub fn revoke_tokens(&self) {
+        self.state.lock().unwrap().token_generation += 1;
+    }
+
+    /// Requests served so far, including logging in.
+    pub fn requests(&self) -> usize {
+        self.state.lock().unwrap().requests
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        // wakes up the listener to see the shutdown
+        TcpStream::connect(self.address).ok();
+    }
+}
+
+/// Welcomes every client on a free local port and records what they send,
+/// until dropped.
+pub struct MockIrcServer {
+    address: SocketAddr,
+    received: Arc<Mutex<Vec<String>>>,
+    connections: Arc<Mutex<Vec<TcpStream>>>,
+    refuse: Arc<AtomicBool>,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl MockIrcServer {
+    pub fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let address = listener.local_addr()?;
+        let received = Arc::<Mutex<Vec<String>>>::default();
+        let connections = Arc::<Mutex<Vec<TcpStream>>>::default();
+        let refuse = Arc::new(AtomicBool::new(false));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let server_received = received.clone();
+        let server_connections = connections.clone();
+        let server_refuse = refuse.clone();
+        let server_shutdown = shutdown.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if server_shutdown.load(Ordering::Relaxed) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    if let Ok(clone) = stream.try_clone() {
+                        server_connections.lock().unwrap().push(clone);
+                    }
+                    let received = server_received.clone();
+                    let refuse = server_refuse.clone();
+                    thread::spawn(move || handle_irc(stream, &received, &refuse).ok());
+                }
+            }
+        });
+
+        Ok(Self {
+            address,
+            received,
+            connections,
+            refuse,
+            shutdown,
+        })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Lines received from all clients so far, e.g. `PRIVMSG #osu :…`.
+    pub fn received(&self) -> Vec<String> {
+        self.received.lock().unwrap().clone()
+    }
+
+    /// Answers later logins with a wrong password error.
+    pub fn refuse_logins(&self) {
+        self.refuse.store(true, Ordering::Relaxed);
+    }
+
+    /// Closes all connections, e.g. to test reconnecting.
+    pub fn disconnect_all(&self) {
+        for connection in self.connections.lock().unwrap().drain(..) {
+            connection.shutdown(Shutdown::Both).ok();
+        }
+    }
+}
+
+impl Drop for MockIrcServer {
+    fn drop(&mut self) {
+        self.disconnect_all();
+        self.shutdown.store(true, Ordering::Relaxed);
+        // wakes up the listener to see the shutdown
+        TcpStream::connect(self.address).ok();
+    }
+}
+
+fn handle_irc(
+    mut stream: TcpStream,
+    received: &Mutex<Vec<String>>,
+    refuse: &AtomicBool,
+) -> io::Result<()> {
+    let reader = BufReader::new(stream.try_clone()?);
+    let mut nick = String::new();
+    for line in reader.lines() {
+        let line = line?;
+        if let Some(new_nick) = line.strip_prefix("NICK ") {
+            nick = new_nick.to_string();
+        } else if line.starts_with("USER ") && refuse.load(Ordering::Relaxed) {
+            write!(stream, ":mock 464 {nick} :Bad authentication token.\r\n")?;
+        } else if line.starts_with("USER ") {
+            write!(stream, ":mock 001 {nick} :Welcome\r\n")?;
+        }
+        received.lock().unwrap().push(line);
+    }
+    Ok(())
+}
+
+/// A beatmap with placeholder metadata.
+pub fn beatmap(beatmap_id: u32, beatmapset_id: u32, status: RankStatus) -> Beatmap {
+    Beatmap {
+        id: beatmap_id,
+        ranked: status,
+        mode: Some(GameMode::Osu),
+        checksum: None,
+        last_updated: Some("2022-01-01T00:00:00Z".to_string()),
+        difficulty_rating: 5.,
+        ar: 9.,
+        accuracy: 8.,
+        cs: 4.,
+        drain: 5.,
+        total_length: 180,
+        count_circles: 300,
+        count_sliders: 200,
+        count_spinners: 1,
+        beatmapset: Beatmapset {
+            id: beatmapset_id,
+            title: format!("Title {beatmapset_id}"),
+            artist: format!("Artist {beatmapset_id}"),
+            creator: "Mapper".to_string(),
+            nominations_summary: None,
+            hype: None,
+            beatmaps: Vec::new(),
+        },
+    }
+}
+
+fn handle(mut stream: TcpStream, state: &Mutex<MockState>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    let mut content_length = 0;
+    let mut authorization = String::new();
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        if header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            } else if name.eq_ignore_ascii_case("authorization") {
+                authorization = value.trim().to_string();
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    let mut request_line = request_line.split_whitespace();
+    let method = request_line.next().unwrap_or_default();
+    let target = request_line.next().unwrap_or_default();
+    let (status, body) = respond(method, target, &authorization, state);
+
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+fn respond(
+    method: &str,
+    target: &str,
+    authorization: &str,
+    state: &Mutex<MockState>,
+) -> (&'static str, String) {
+    const OK: &str = "200 OK";
+    const NOT_FOUND: (&str, String) = ("404 Not Found", String::new());
+    const UNAUTHORIZED: (&str, String) = ("401 Unauthorized", String::new());
+
+    let mut state = state.lock().unwrap();
+    state.requests += 1;
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    if method == "POST" && path == "/oauth/token" {
+        let token = json!({
+            "access_token": format!("mock-{}", state.token_generation),
+            "expires_in": 86400,
+            "token_type": "Bearer",
+        });
+        return (OK, token.to_string());
+    }
+    if method != "GET" {
+        return NOT_FOUND;
+    }
+    if authorization != format!("Bearer mock-{}", state.token_generation) {
+        return UNAUTHORIZED;
+    }
+
+    if path == "/api/v2/beatmaps" {
+        let beatmaps = query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .filter(|(key, _)| matches!(*key, "ids[]" | "ids%5B%5D"))
+            .filter_map(|(_, value)| value.parse::<u32>().ok())
+            .filter_map(|beatmap_id| state.beatmaps.get(&beatmap_id))
+            .collect::<Vec<_>>();
+        (OK, json!({ "beatmaps": beatmaps }).to_string())
+    } else if let Some(beatmap_id) = path.strip_prefix("/api/v2/beatmaps/") {
+        beatmap_id
+            .parse::<u32>()
+            .ok()
+            .and_then(|beatmap_id| state.beatmaps.get(&beatmap_id))
+            .map_or(NOT_FOUND, |beatmap| (OK, json!(beatmap).to_string()))
+    } else if let Some(beatmapset_id) = path.strip_prefix("/api/v2/beatmapsets/") {
+        beatmapset_id
+            .parse::<u32>()
+            .ok()
+            .and_then(|beatmapset_id| {
+                state
+                    .beatmaps
+                    .values()
+                    .find(|beatmap| beatmap.beatmapset.id == beatmapset_id)
+            })
+            .map_or(NOT_FOUND, |beatmap| {
+                let mut beatmapset = beatmap.beatmapset.clone();
+                beatmapset.beatmaps = state
+                    .beatmaps
+                    .values()
+                    .filter(|other| other.beatmapset.id == beatmapset.id)
+                    .map(|other| BeatmapsetBeatmap {
+                        id: other.id,
+                        mode: other.mode,
+                        version: format!("Difficulty {}", other.id),
+                    })
+                    .collect();
+                beatmapset.beatmaps.sort_by_key(|beatmap| beatmap.id);
+                (OK, json!(beatmapset).to_string())
+            })
+    } else {
+        NOT_FOUND
+    }
+}
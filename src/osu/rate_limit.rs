@@ -0,0 +1,158 @@
+//! Token bucket shared by all API requests, as the osu! API asks clients to
+//! stay under about 60 requests per minute.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+pub const DEFAULT_BUDGET: u32 = 60;
+/// Budgets are clamped to `1..=MAX_BUDGET`, without a budget nothing could
+/// ever be requested.
+pub const MAX_BUDGET: u32 = 120;
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy)]
+pub struct RateLimitStats {
+    /// Requests per minute.
+    pub budget: u32,
+    /// Requests made in the last minute.
+    pub recent: u32,
+    /// Requests which can be made right away.
+    pub available: u32,
+    /// Requests waiting for their turn.
+    pub queued: u32,
+}
+
+struct Bucket {
+    budget: u32,
+    tokens: f64,
+    refilled: Instant,
+    recent: VecDeque<Instant>,
+    queued: u32,
+}
+
+impl Bucket {
+    /// Up to ten seconds' worth of requests can be made at once.
+    fn capacity(&self) -> f64 {
+        (f64::from(self.budget) / 6.).max(1.)
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * f64::from(self.budget) / 60.).min(self.capacity());
+        self.refilled = now;
+        while self
+            .recent
+            .front()
+            .is_some_and(|request| now.duration_since(*request) >= MINUTE)
+        {
+            self.recent.pop_front();
+        }
+    }
+
+    /// Takes a token, or returns how long until there is one.
+    fn take(&mut self) -> Result<(), Duration> {
+        let now = Instant::now();
+        self.refill(now);
+        if self.tokens >= 1. {
+            self.tokens -= 1.;
+            self.recent.push_back(now);
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1. - self.tokens) * 60. / f64::from(self.budget),
+            ))
+        }
+    }
+}
+
+pub struct RateLimiter {
+    /// Tokio's mutex queues waiters in order, so requests are served first
+    /// come, first served and no watch can starve the others.
+    turn: tokio::sync::Mutex<()>,
+    bucket: Mutex<Bucket>,
+    /// Wakes the waiting request to wait according to the new budget.
+    budget_changed: Notify,
+}
+
+impl RateLimiter {
+    pub fn new(budget: u32) -> Self {
+        let mut bucket = Bucket {
+            budget: budget.clamp(1, MAX_BUDGET),
+            tokens: 0.,
+            refilled: Instant::now(),
+            recent: VecDeque::new(),
+            queued: 0,
+        };
+        bucket.tokens = bucket.capacity();
+        Self {
+            turn: tokio::sync::Mutex::new(()),
+            bucket: Mutex::new(bucket),
+            budget_changed: Notify::new(),
+        }
+    }
+
+    pub fn set_budget(&self, budget: u32) {
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.refill(Instant::now());
+        bucket.budget = budget.clamp(1, MAX_BUDGET);
+        bucket.tokens = bucket.tokens.min(bucket.capacity());
+        self.budget_changed.notify_waiters();
+    }
+
+    /// Waits until a request may be made.
+    pub async fn acquire(&self) {
+        let _queued = Queued::new(&self.bucket);
+        let _turn = self.turn.lock().await;
+        loop {
+            // registered before taking, so no change is missed
+            let budget_changed = self.budget_changed.notified();
+            tokio::pin!(budget_changed);
+            budget_changed.as_mut().enable();
+            let wait = self.bucket.lock().unwrap().take();
+            match wait {
+                Ok(()) => break,
+                Err(wait) => {
+                    tokio::select! {
+                        () = tokio::time::sleep(wait) => (),
+                        () = budget_changed => (),
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn stats(&self) -> RateLimitStats {
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.refill(Instant::now());
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let available = bucket.tokens.floor() as u32;
+        RateLimitStats {
+            budget: bucket.budget,
+            recent: bucket.recent.len().try_into().unwrap_or(u32::MAX),
+            available,
+            queued: bucket.queued,
+        }
+    }
+}
+
+/// Counts a request as queued until it is dropped, also when its task is
+/// aborted while waiting.
+struct Queued<'a>(&'a Mutex<Bucket>);
+
+impl<'a> Queued<'a> {
+    fn new(bucket: &'a Mutex<Bucket>) -> Self {
+        bucket.lock().unwrap().queued += 1;
+        Self(bucket)
+    }
+}
+
+impl Drop for Queued<'_> {
+    fn drop(&mut self) {
+        self.0.lock().unwrap().queued -= 1;
+    }
+}
@@ -1,9 +1,9 @@
 use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
-use serde_repr::Deserialize_repr;
+use serde_repr::{Deserialize_repr, Serialize_repr};
 
-#[derive(Clone, Copy, Deserialize_repr)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize_repr, Deserialize_repr)]
 #[repr(i8)]
 pub enum RankStatus {
     Graveyard = -2,
@@ -15,6 +15,18 @@ pub enum RankStatus {
     Loved = 4,
 }
 
+impl RankStatus {
+    pub const ALL: [Self; 7] = [
+        Self::Graveyard,
+        Self::Wip,
+        Self::Pending,
+        Self::Ranked,
+        Self::Approved,
+        Self::Qualified,
+        Self::Loved,
+    ];
+}
+
 impl Display for RankStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(match self {
@@ -29,20 +41,245 @@ impl Display for RankStatus {
     }
 }
 
-#[derive(Deserialize)]
+/// The ruleset a beatmap is played in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GameMode {
+    Osu,
+    Taiko,
+    /// osu!catch
+    Fruits,
+    Mania,
+}
+
+impl GameMode {
+    pub const ALL: [Self; 4] = [Self::Osu, Self::Taiko, Self::Fruits, Self::Mania];
+}
+
+impl Display for GameMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            GameMode::Osu => "osu!",
+            GameMode::Taiko => "osu!taiko",
+            GameMode::Fruits => "osu!catch",
+            GameMode::Mania => "osu!mania",
+        })
+    }
+}
+
+/// Beatmapset cover images, see
+/// <https://osu.ppy.sh/docs/index.html#beatmapsetcompact-covers>.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum CoverVariant {
+    /// 900×250
+    Cover,
+    /// 400×140
+    Card,
+    /// 1920×360
+    SlimCover,
+    /// 150×150
+    List,
+}
+
+impl CoverVariant {
+    /// The variants wide enough for a banner, narrowest first.
+    pub const BANNERS: [Self; 3] = [Self::Card, Self::Cover, Self::SlimCover];
+
+    /// Width and height in pixels, doubled in the `@2x` form.
+    pub fn size(self) -> (u32, u32) {
+        match self {
+            CoverVariant::Cover => (900, 250),
+            CoverVariant::Card => (400, 140),
+            CoverVariant::SlimCover => (1920, 360),
+            CoverVariant::List => (150, 150),
+        }
+    }
+
+    /// `high_dpi` selects the `@2x` form at twice the resolution.
+    pub fn url(self, beatmapset_id: u32, high_dpi: bool) -> String {
+        let name = match self {
+            CoverVariant::Cover => "cover",
+            CoverVariant::Card => "card",
+            CoverVariant::SlimCover => "slimcover",
+            CoverVariant::List => "list",
+        };
+        let scale = if high_dpi { "@2x" } else { "" };
+        format!("https://assets.ppy.sh/beatmaps/{beatmapset_id}/covers/{name}{scale}.jpg")
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct RequiredNominations {
+    pub main_ruleset: u32,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct NominationsSummary {
+    pub current: u32,
+    required: Option<u32>,
+    required_meta: Option<RequiredNominations>,
+}
+
+impl NominationsSummary {
+    /// Older API responses report `required` directly, newer ones only per
+    /// ruleset in `required_meta`.
+    pub fn required(&self) -> Option<u32> {
+        self.required
+            .or_else(|| self.required_meta.map(|meta| meta.main_ruleset))
+    }
+
+    /// The required count for display, `?` if the API didn't report it.
+    pub fn required_text(&self) -> String {
+        self.required()
+            .map_or_else(|| "?".to_string(), |required| required.to_string())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Hype {
+    pub current: u32,
+    pub required: u32,
+}
+
+/// A difficulty as listed in a full beatmapset.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BeatmapsetBeatmap {
+    pub id: u32,
+    #[serde(default)]
+    pub mode: Option<GameMode>,
+    /// The difficulty name.
+    #[serde(default)]
+    pub version: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Beatmapset {
+    pub id: u32,
     pub title: String,
     pub artist: String,
     pub creator: String,
+    pub nominations_summary: Option<NominationsSummary>,
+    pub hype: Option<Hype>,
+    /// Only included when looking up the beatmapset itself.
+    #[serde(default)]
+    pub beatmaps: Vec<BeatmapsetBeatmap>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Beatmap {
     pub id: u32,
     pub ranked: RankStatus,
+    /// `None` for beatmaps saved before the mode was recorded.
+    #[serde(default)]
+    pub mode: Option<GameMode>,
+    /// MD5 of the `.osu` file, changing with every update.
+    pub checksum: Option<String>,
+    /// ISO 8601 time of the last update.
+    #[serde(default)]
+    pub last_updated: Option<String>,
+    /// Star rating.
+    #[serde(default)]
+    pub difficulty_rating: f32,
+    #[serde(default)]
+    pub ar: f32,
+    /// Overall difficulty.
+    #[serde(default)]
+    pub accuracy: f32,
+    #[serde(default)]
+    pub cs: f32,
+    /// HP drain rate.
+    #[serde(default)]
+    pub drain: f32,
+    /// Seconds.
+    #[serde(default)]
+    pub total_length: u32,
+    #[serde(default)]
+    pub count_circles: u32,
+    #[serde(default)]
+    pub count_sliders: u32,
+    #[serde(default)]
+    pub count_spinners: u32,
     pub beatmapset: Beatmapset,
 }
 
+impl Beatmap {
+    pub fn attributes(&self) -> BeatmapAttributes {
+        BeatmapAttributes {
+            stars: self.difficulty_rating,
+            ar: self.ar,
+            od: self.accuracy,
+            cs: self.cs,
+            hp: self.drain,
+            length: self.total_length,
+            circles: self.count_circles,
+            sliders: self.count_sliders,
+            spinners: self.count_spinners,
+        }
+    }
+}
+
+/// The difficulty of one version of a beatmap, to compare it across updates.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BeatmapAttributes {
+    pub stars: f32,
+    pub ar: f32,
+    pub od: f32,
+    pub cs: f32,
+    pub hp: f32,
+    /// Seconds.
+    pub length: u32,
+    pub circles: u32,
+    pub sliders: u32,
+    pub spinners: u32,
+}
+
+impl BeatmapAttributes {
+    /// The attributes which differ in `new`, e.g. `AR 9 → 9.3`.
+    pub fn changes(&self, new: &Self) -> Vec<String> {
+        fn length(seconds: u32) -> String {
+            format!("{}:{:02}", seconds / 60, seconds % 60)
+        }
+
+        let mut changes = Vec::new();
+        if (self.stars - new.stars).abs() >= 0.005 {
+            changes.push(format!("★ {:.2} → {:.2}", self.stars, new.stars));
+        }
+        for (name, old, new) in [
+            ("AR", self.ar, new.ar),
+            ("OD", self.od, new.od),
+            ("CS", self.cs, new.cs),
+            ("HP", self.hp, new.hp),
+        ] {
+            #[allow(clippy::float_cmp)]
+            if old != new {
+                changes.push(format!("{name} {old} → {new}"));
+            }
+        }
+        if self.length != new.length {
+            changes.push(format!(
+                "Length {} → {}",
+                length(self.length),
+                length(new.length)
+            ));
+        }
+        for (name, old, new) in [
+            ("Circles", self.circles, new.circles),
+            ("Sliders", self.sliders, new.sliders),
+            ("Spinners", self.spinners, new.spinners),
+        ] {
+            if old != new {
+                changes.push(format!("{name} {old} → {new}"));
+            }
+        }
+        changes
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Beatmaps {
+    pub beatmaps: Vec<Beatmap>,
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum GrantType {
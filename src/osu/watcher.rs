@@ -0,0 +1,294 @@
+//! Polling a beatmap for changes until its status settles.
+
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_core::Stream;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use super::api::{ApiError, Backoff, OsuApi};
+use super::history;
+use super::metrics::Metrics;
+use super::types::{Beatmap, BeatmapAttributes, NominationsSummary, RankStatus};
+
+/// Statuses which are not expected to change anymore.
+pub const DEFAULT_STOP_STATUSES: [RankStatus; 4] = [
+    RankStatus::Graveyard,
+    RankStatus::Wip,
+    RankStatus::Ranked,
+    RankStatus::Loved,
+];
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub enum Transition {
+    StatusChanged {
+        from: RankStatus,
+        to: RankStatus,
+    },
+    NominationAdded(NominationsSummary),
+    NominationRemoved(NominationsSummary),
+    /// The mapper changed the beatmap's content.
+    Updated {
+        from: BeatmapAttributes,
+        to: BeatmapAttributes,
+    },
+}
+
+impl Transition {
+    fn between(old: &Beatmap, new: &Beatmap) -> Vec<Self> {
+        let mut transitions = Vec::new();
+        if old.ranked != new.ranked {
+            transitions.push(Self::StatusChanged {
+                from: old.ranked,
+                to: new.ranked,
+            });
+        }
+        if let (Some(old), Some(new)) = (
+            old.beatmapset.nominations_summary,
+            new.beatmapset.nominations_summary,
+        ) {
+            match new.current.cmp(&old.current) {
+                Ordering::Greater => transitions.push(Self::NominationAdded(new)),
+                Ordering::Less => transitions.push(Self::NominationRemoved(new)),
+                Ordering::Equal => (),
+            }
+        }
+        // either is missing in beatmaps restored from the history
+        let changed = |old: &Option<String>, new: &Option<String>| {
+            old.is_some() && new.is_some() && old != new
+        };
+        if changed(&old.checksum, &new.checksum) || changed(&old.last_updated, &new.last_updated) {
+            transitions.push(Self::Updated {
+                from: old.attributes(),
+                to: new.attributes(),
+            });
+        }
+        transitions
+    }
+}
+
+impl Display for Transition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Transition::StatusChanged { from, to } => write!(f, "{from} → {to}"),
+            Transition::NominationAdded(nominations) => write!(
+                f,
+                "Nomination added ({}/{})",
+                nominations.current,
+                nominations.required_text()
+            ),
+            Transition::NominationRemoved(nominations) => write!(
+                f,
+                "Nomination removed ({}/{})",
+                nominations.current,
+                nominations.required_text()
+            ),
+            Transition::Updated { from, to } => {
+                f.write_str("Updated")?;
+                let changes = from.changes(to);
+                if !changes.is_empty() {
+                    write!(f, ": {}", changes.join(", "))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum WatchEvent {
+    /// The result of every successful poll.
+    Beatmap(Beatmap),
+    /// A change since the previous poll, sent before the new beatmap.
+    Transition(Transition),
+    /// The API could not be reached and polling is retried after `retry_in`,
+    /// the beatmap last sent is now stale.
+    Offline { retry_in: Duration },
+    /// The API responded with an error. Polling is retried after `retry_in` if
+    /// the error may be temporary, otherwise the stream ends.
+    Error {
+        error: ApiError,
+        retry_in: Option<Duration>,
+    },
+    /// The beatmap does not exist, ending the stream.
+    NotFound,
+}
+
+/// A stream of [`WatchEvent`]s about one beatmap, polled in the background
+/// until the stream is dropped. It ends after a beatmap with one of the stop
+/// statuses.
+///
+/// ```
+/// use futures_util::StreamExt;
+/// use osu_beatmap_watcher::osu::mock::{self, MockServer};
+/// use osu_beatmap_watcher::osu::types::RankStatus;
+/// use osu_beatmap_watcher::osu::{OsuApi, WatchEvent};
+///
+/// let server = MockServer::start().unwrap();
+/// server.set_beatmap(mock::beatmap(75, 1, RankStatus::Pending));
+///
+/// tokio::runtime::Runtime::new().unwrap().block_on(async {
+///     let api = OsuApi::new(server.url())
+///         .log_in("id", "secret")
+///         .await
+///         .unwrap();
+///     let mut watcher = api.watch(75, vec![RankStatus::Ranked], None);
+///
+///     assert!(matches!(watcher.next().await, Some(WatchEvent::Beatmap(_))));
+///     server.set_status(75, RankStatus::Ranked);
+///     assert!(matches!(
+///         watcher.next().await,
+///         Some(WatchEvent::Transition(_))
+///     ));
+///     assert!(matches!(watcher.next().await, Some(WatchEvent::Beatmap(_))));
+///     assert!(watcher.next().await.is_none());
+/// });
+/// ```
+pub struct Watcher {
+    beatmap_id: u32,
+    rx: mpsc::UnboundedReceiver<WatchEvent>,
+    task: JoinHandle<()>,
+}
+
+impl Watcher {
+    pub(super) fn spawn(
+        api: OsuApi,
+        beatmap_id: u32,
+        stop_statuses: Vec<RankStatus>,
+        previous: Option<Beatmap>,
+    ) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let task = tokio::spawn(poll(api, beatmap_id, stop_statuses, previous, tx));
+        Self {
+            beatmap_id,
+            rx,
+            task,
+        }
+    }
+
+    pub fn beatmap_id(&self) -> u32 {
+        self.beatmap_id
+    }
+}
+
+impl Stream for Watcher {
+    type Item = WatchEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Counts a watch as active until its task ends or is aborted.
+struct ActiveWatch(Arc<Metrics>);
+
+impl ActiveWatch {
+    fn new(metrics: Arc<Metrics>) -> Self {
+        metrics.watch_started();
+        Self(metrics)
+    }
+}
+
+impl Drop for ActiveWatch {
+    fn drop(&mut self) {
+        self.0.watch_stopped();
+    }
+}
+
+async fn poll(
+    api: OsuApi,
+    beatmap_id: u32,
+    stop_statuses: Vec<RankStatus>,
+    mut previous: Option<Beatmap>,
+    tx: mpsc::UnboundedSender<WatchEvent>,
+) {
+    if !api.is_logged_in() {
+        warn!(beatmap_id, "Cannot watch without logging in");
+        return;
+    }
+
+    info!(beatmap_id, "Started watching");
+    let metrics = api.metrics();
+    let _active = ActiveWatch::new(metrics.clone());
+    let mut backoff = Backoff::new();
+    loop {
+        match api.beatmap(beatmap_id).await {
+            Ok(Some(mut beatmap)) => {
+                backoff = Backoff::new();
+                // nominations and hype are only included in the full beatmapset
+                if matches!(beatmap.ranked, RankStatus::Pending | RankStatus::Qualified) {
+                    match api.beatmapset(beatmap.beatmapset.id).await {
+                        Ok(Some(beatmapset)) => beatmap.beatmapset = beatmapset,
+                        Ok(None) => (),
+                        Err(err) => warn!(beatmap_id, "Fetching beatmapset failed: {err}"),
+                    }
+                }
+
+                if let Some(previous) = &previous {
+                    for transition in Transition::between(previous, &beatmap) {
+                        info!(beatmap_id, "{transition}");
+                        tx.send(WatchEvent::Transition(transition)).ok();
+                    }
+                }
+                previous = Some(beatmap.clone());
+
+                let ranked = beatmap.ranked;
+                metrics.record_poll(beatmap_id, ranked, history::now());
+                tx.send(WatchEvent::Beatmap(beatmap)).ok();
+                if stop_statuses.contains(&ranked) {
+                    info!(beatmap_id, "Stopped watching at {ranked}");
+                    break;
+                }
+            }
+            Ok(None) => {
+                warn!(beatmap_id, "Beatmap not found");
+                tx.send(WatchEvent::NotFound).ok();
+                break;
+            }
+            Err(err) if err.is_network() || err.is_temporary() => {
+                // keeps watching until the network or the API is back
+                let retry_in = backoff.next_delay();
+                warn!(
+                    beatmap_id,
+                    "Polling failed, retrying in {}s: {err}",
+                    retry_in.as_secs()
+                );
+                let event = if err.is_network() {
+                    WatchEvent::Offline { retry_in }
+                } else {
+                    WatchEvent::Error {
+                        error: err,
+                        retry_in: Some(retry_in),
+                    }
+                };
+                tx.send(event).ok();
+                tokio::time::sleep(retry_in).await;
+                continue;
+            }
+            Err(err) => {
+                warn!(beatmap_id, "Polling failed, stopped watching: {err}");
+                tx.send(WatchEvent::Error {
+                    error: err,
+                    retry_in: None,
+                })
+                .ok();
+                break;
+            }
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
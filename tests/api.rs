@@ -0,0 +1,26 @@
+use osu_beatmap_watcher::osu::mock::{self, MockServer};
+use osu_beatmap_watcher::osu::types::RankStatus;
+use osu_beatmap_watcher::osu::OsuApi;
+
+#[tokio::test]
+async fn logs_in_again_when_the_token_is_rejected() {
+    let server = MockServer::start().unwrap();
+    server.set_beatmap(mock::beatmap(75, 1, RankStatus::Pending));
+    let api = OsuApi::new(server.url())
+        .log_in("id", "secret")
+        .await
+        .unwrap();
+
+    assert!(api.beatmapset(1).await.unwrap().is_some());
+    server.revoke_tokens();
+    assert!(api.beatmapset(1).await.unwrap().is_some());
+    assert!(api.beatmap(75).await.unwrap().is_some());
+    // logging in, the lookup, the rejected lookup, logging in again and the
+    // retried lookup, then one more
+    assert_eq!(server.requests(), 6);
+    // logging in the first time is no refresh
+    assert!(api
+        .metrics()
+        .render()
+        .contains("\nosu_token_refreshes_total 1\n"));
+}
@@ -0,0 +1,70 @@
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use osu_beatmap_watcher::osu::beatmap_file::{self, Metadata};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures")).join(name)
+}
+
+fn sorted(mut metadata: Vec<Metadata>) -> Vec<Metadata> {
+    metadata.sort_by(|a, b| a.version.cmp(&b.version));
+    metadata
+}
+
+#[test]
+fn reads_metadata() {
+    let metadata = beatmap_file::read_metadata_from_path(&fixture(
+        "beatmapset/Artist A - Song A (Mapper) [Insane].osu",
+    ))
+    .unwrap();
+
+    assert_eq!(metadata.len(), 1);
+    assert_eq!(metadata[0].title, "Song A");
+    assert_eq!(metadata[0].artist, "Artist A");
+    assert_eq!(metadata[0].creator, "Mapper");
+    // not overwritten by the key in [Difficulty]
+    assert_eq!(metadata[0].version, "Insane");
+    assert_eq!(metadata[0].beatmap_id, Some(1001));
+    assert_eq!(metadata[0].beatmapset_id, Some(501));
+}
+
+#[test]
+fn reads_old_and_unsubmitted_beatmaps() {
+    let old = beatmap_file::read_metadata_from_path(&fixture("v9.osu")).unwrap();
+    assert_eq!(old[0].version, "Normal");
+    assert_eq!(old[0].beatmap_id, None);
+    assert_eq!(old[0].beatmapset_id, Some(42));
+
+    let unsubmitted = beatmap_file::read_metadata_from_path(&fixture("unsubmitted.osu")).unwrap();
+    assert_eq!(unsubmitted[0].beatmap_id, None);
+    assert_eq!(unsubmitted[0].beatmapset_id, None);
+}
+
+#[test]
+fn reads_folders_and_archives() {
+    for path in ["beatmapset", "beatmapset.osz"] {
+        let metadata = sorted(beatmap_file::read_metadata_from_path(&fixture(path)).unwrap());
+
+        let ids = metadata
+            .iter()
+            .map(|metadata| (metadata.version.as_str(), metadata.beatmap_id))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            [("Extra", Some(1002)), ("Insane", Some(1001))],
+            "{path}"
+        );
+    }
+}
+
+#[test]
+fn rejects_other_files() {
+    assert!(beatmap_file::read_metadata_from_path(&fixture("collection.db")).is_err());
+    assert!(
+        beatmap_file::read_metadata(Cursor::new("[Metadata]\nTitle"))
+            .unwrap()
+            .title
+            .is_empty()
+    );
+}
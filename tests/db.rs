@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+
+use osu_beatmap_watcher::osu::db;
+
+const COLLECTION_DB: &[u8] = include_bytes!("fixtures/collection.db");
+const OSU_DB: &[u8] = include_bytes!("fixtures/osu!.db");
+
+#[test]
+fn reads_collections() {
+    let collections = db::read_collections(COLLECTION_DB).unwrap();
+
+    let names = collections
+        .iter()
+        .map(|collection| collection.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["to watch", "empty", "qualified"]);
+    assert_eq!(collections[0].beatmap_hashes.len(), 3);
+    assert!(collections[1].beatmap_hashes.is_empty());
+    assert_eq!(
+        collections[2].beatmap_hashes,
+        ["fedcba9876543210fedcba9876543210"]
+    );
+}
+
+#[test]
+fn reads_beatmaps() {
+    let beatmaps = db::read_beatmaps(OSU_DB).unwrap();
+
+    assert_eq!(beatmaps.len(), 3);
+    assert_eq!(beatmaps[0].artist, "Artist A");
+    assert_eq!(beatmaps[0].title, "Song A");
+    assert_eq!(beatmaps[0].difficulty, "Insane");
+    assert_eq!(beatmaps[0].md5, "0123456789abcdef0123456789abcdef");
+    assert_eq!(beatmaps[0].beatmap_id, Some(1001));
+    assert_eq!(beatmaps[0].beatmapset_id, Some(501));
+    assert_eq!(beatmaps[1].difficulty, "Extra");
+    assert_eq!(beatmaps[1].beatmap_id, Some(1002));
+    assert_eq!(beatmaps[2].beatmap_id, None);
+    assert_eq!(beatmaps[2].beatmapset_id, None);
+}
+
+#[test]
+fn resolves_collection() {
+    let collections = db::read_collections(COLLECTION_DB).unwrap();
+    let beatmaps = db::read_beatmaps(OSU_DB).unwrap();
+    let beatmaps = beatmaps
+        .iter()
+        .map(|beatmap| (beatmap.md5.as_str(), beatmap))
+        .collect::<HashMap<_, _>>();
+
+    assert_eq!(
+        db::resolve_collection(&collections[0], &beatmaps),
+        (vec![1001], 2)
+    );
+    assert_eq!(
+        db::resolve_collection(&collections[2], &beatmaps),
+        (vec![1002], 0)
+    );
+}
+
+#[test]
+fn rejects_truncated_files() {
+    assert!(db::read_collections(&COLLECTION_DB[..COLLECTION_DB.len() - 1]).is_err());
+    assert!(db::read_beatmaps(&OSU_DB[..OSU_DB.len() / 2]).is_err());
+    assert!(db::read_collections(&[0x00, 0x01][..]).is_err());
+}
+
+#[test]
+fn rejects_invalid_strings() {
+    let mut collection_db = COLLECTION_DB.to_vec();
+    // marker of the first collection name
+    collection_db[8] = 0x0A;
+    assert!(db::read_collections(collection_db.as_slice()).is_err());
+}
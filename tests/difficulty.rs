@@ -0,0 +1,32 @@
+use osu_beatmap_watcher::osu::difficulty::Mods;
+
+#[test]
+fn toggles_mods_off_and_conflicting_ones_away() {
+    let mods = Mods::NONE.toggle(Mods::HIDDEN).toggle(Mods::HARD_ROCK);
+    assert!(mods.contains(Mods::HIDDEN) && mods.contains(Mods::HARD_ROCK));
+
+    let mods = mods.toggle(Mods::EASY);
+    assert!(mods.contains(Mods::EASY) && mods.contains(Mods::HIDDEN));
+    assert!(!mods.contains(Mods::HARD_ROCK));
+
+    let mods = mods.toggle(Mods::DOUBLE_TIME).toggle(Mods::HALF_TIME);
+    assert!(mods.contains(Mods::HALF_TIME));
+    assert!(!mods.contains(Mods::DOUBLE_TIME));
+
+    let mods = mods
+        .toggle(Mods::EASY)
+        .toggle(Mods::HIDDEN)
+        .toggle(Mods::HALF_TIME);
+    assert_eq!(mods, Mods::NONE);
+}
+
+#[test]
+fn displays_acronyms_in_order() {
+    assert_eq!(Mods::NONE.to_string(), "NM");
+    assert_eq!(Mods::FLASHLIGHT.toggle(Mods::HIDDEN).to_string(), "HDFL");
+    let mods = Mods::DOUBLE_TIME
+        .toggle(Mods::HARD_ROCK)
+        .toggle(Mods::HIDDEN);
+    assert_eq!(mods.to_string(), "HDHRDT");
+    assert_eq!(mods.bits(), 8 + 16 + 64);
+}
@@ -0,0 +1,29 @@
+use std::{env, fs, process};
+
+use osu_beatmap_watcher::osu::download;
+
+#[test]
+fn finds_installed_beatmapsets_by_id() {
+    let songs_path = env::temp_dir().join(format!("osu-beatmap-watcher-songs-{}", process::id()));
+    fs::create_dir_all(songs_path.join("1234 Artist - Title")).unwrap();
+    fs::create_dir_all(songs_path.join("12345 Other - Title")).unwrap();
+    fs::create_dir_all(songs_path.join("99")).unwrap();
+    fs::write(songs_path.join("77 Not - A Folder"), "").unwrap();
+
+    let installed = |beatmapset_id| download::installed(&songs_path, beatmapset_id).unwrap();
+    assert_eq!(
+        installed(1234),
+        Some(songs_path.join("1234 Artist - Title"))
+    );
+    assert_eq!(installed(99), Some(songs_path.join("99")));
+    assert_eq!(installed(123), None);
+    assert_eq!(installed(77), None);
+
+    fs::remove_dir_all(&songs_path).unwrap();
+}
+
+#[test]
+fn fails_without_a_songs_folder() {
+    let songs_path = env::temp_dir().join("osu-beatmap-watcher-no-songs");
+    assert!(download::installed(&songs_path, 1).is_err());
+}
@@ -0,0 +1,136 @@
+use osu_beatmap_watcher::osu::history::{ExportContent, ExportFormat, History};
+
+const PENDING: i8 = 0;
+const QUALIFIED: i8 = 3;
+
+/// Observations of beatmap 1 as `(status, nominations, first_seen, last_seen)`,
+/// with one of beatmap 2 in between.
+fn history(observations: &[(i8, u32, u64, u64)]) -> History {
+    let mut json = observations
+        .iter()
+        .map(|(status, nominations, first_seen, last_seen)| {
+            format!(
+                r#"{{"beatmap_id": 1, "beatmapset_id": 10, "artist": "Artist", "title": "Title",
+                    "status": {status}, "nominations": {nominations},
+                    "first_seen": {first_seen}, "last_seen": {last_seen}}}"#
+            )
+        })
+        .collect::<Vec<_>>();
+    json.insert(
+        1,
+        r#"{"beatmap_id": 2, "beatmapset_id": 20, "artist": "Other", "title": "Other",
+            "status": 1, "nominations": null, "first_seen": 0, "last_seen": 1000}"#
+            .to_string(),
+    );
+    serde_json::from_str(&format!(r#"{{"observations": [{}]}}"#, json.join(","))).unwrap()
+}
+
+#[test]
+fn merges_observations_with_the_same_status() {
+    let history = history(&[
+        (PENDING, 0, 0, 100),
+        (PENDING, 1, 150, 200),
+        (QUALIFIED, 2, 300, 400),
+    ]);
+    let periods = history.status_periods(1);
+
+    assert_eq!(periods.len(), 2);
+    assert_eq!(periods[0].previous_status, None);
+    assert_eq!(periods[0].status, "Pending");
+    assert_eq!(periods[0].entered_at, 0);
+    assert_eq!(periods[0].left_at, Some(300));
+    assert_eq!(periods[0].duration_secs, 300);
+    assert_eq!(periods[1].previous_status.as_deref(), Some("Pending"));
+    assert_eq!(periods[1].status, "Qualified");
+}
+
+#[test]
+fn keeps_statuses_flipping_back_apart() {
+    let history = history(&[
+        (PENDING, 0, 0, 100),
+        (QUALIFIED, 2, 200, 300),
+        (PENDING, 1, 400, 500),
+    ]);
+    let periods = history.status_periods(1);
+
+    let statuses = periods
+        .iter()
+        .map(|period| (period.previous_status.as_deref(), period.status.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        statuses,
+        [
+            (None, "Pending"),
+            (Some("Pending"), "Qualified"),
+            (Some("Qualified"), "Pending"),
+        ]
+    );
+    assert_eq!(periods[1].entered_at, 200);
+    assert_eq!(periods[1].left_at, Some(400));
+    assert_eq!(periods[1].duration_secs, 200);
+}
+
+#[test]
+fn last_period_is_open() {
+    let history = history(&[(PENDING, 0, 0, 100), (QUALIFIED, 2, 200, 350)]);
+    let periods = history.status_periods(1);
+
+    assert_eq!(periods[1].left_at, None);
+    assert_eq!(periods[1].duration_secs, 150);
+}
+
+#[test]
+fn out_of_order_times_do_not_overflow() {
+    let history = history(&[(PENDING, 0, 500, 600), (QUALIFIED, 2, 100, 50)]);
+    let periods = history.status_periods(1);
+
+    assert_eq!(periods[0].duration_secs, 0);
+    assert_eq!(periods[1].duration_secs, 0);
+}
+
+fn export(history: &History, content: ExportContent, format: ExportFormat) -> String {
+    let mut output = Vec::new();
+    history.export(&[1], content, format, &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn exports_status_periods() {
+    let history = history(&[(PENDING, 0, 0, 100), (PENDING, 1, 150, 200)]);
+
+    let csv = export(&history, ExportContent::StatusPeriods, ExportFormat::Csv);
+    assert_eq!(
+        csv,
+        "beatmap_id,beatmapset_id,artist,title,previous_status,status,entered_at,left_at,duration_secs\n\
+         1,10,Artist,Title,,Pending,0,,200\n"
+    );
+}
+
+#[test]
+fn exports_every_observation_with_nominations() {
+    let history = history(&[
+        (PENDING, 0, 0, 100),
+        (PENDING, 1, 150, 200),
+        (QUALIFIED, 2, 300, 400),
+    ]);
+
+    let csv = export(&history, ExportContent::Observations, ExportFormat::Csv);
+    assert_eq!(
+        csv,
+        "beatmap_id,beatmapset_id,artist,title,creator,mode,status,nominations,first_seen,last_seen\n\
+         1,10,Artist,Title,,,Pending,0,0,100\n\
+         1,10,Artist,Title,,,Pending,1,150,200\n\
+         1,10,Artist,Title,,,Qualified,2,300,400\n"
+    );
+
+    let json = export(&history, ExportContent::Observations, ExportFormat::Json);
+    let observations: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let nominations = observations
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|observation| observation["nominations"].as_u64().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(nominations, [0, 1, 2]);
+    assert_eq!(observations[2]["status"], "Qualified");
+}
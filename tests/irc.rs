@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use osu_beatmap_watcher::osu::irc::{IrcConfig, IrcStatus, Notifier};
+use osu_beatmap_watcher::osu::mock::MockIrcServer;
+
+fn config(server: &MockIrcServer) -> IrcConfig {
+    IrcConfig {
+        server: server.address().to_string(),
+        nick: "watcher".to_string(),
+        password: "password".to_string(),
+        target: "#osu".to_string(),
+    }
+}
+
+/// Waits up to ten seconds for `condition`.
+async fn wait_until(mut condition: impl FnMut() -> bool) {
+    for _ in 0..100 {
+        if condition() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("timed out");
+}
+
+fn logins(server: &MockIrcServer) -> usize {
+    server
+        .received()
+        .iter()
+        .filter(|line| line.starts_with("USER "))
+        .count()
+}
+
+#[tokio::test]
+async fn sends_queued_messages_after_reconnecting() {
+    let server = MockIrcServer::start().unwrap();
+    let notifier = Notifier::spawn(config(&server));
+    wait_until(|| notifier.is_connected()).await;
+
+    server.disconnect_all();
+    wait_until(|| !notifier.is_connected()).await;
+    notifier.send("Qualified → Ranked");
+    wait_until(|| {
+        server
+            .received()
+            .contains(&"PRIVMSG #osu :Qualified → Ranked".to_string())
+    })
+    .await;
+
+    assert_eq!(logins(&server), 2);
+    assert_eq!(notifier.status(), IrcStatus::Connected);
+}
+
+#[tokio::test]
+async fn stops_when_the_password_is_refused() {
+    let server = MockIrcServer::start().unwrap();
+    server.refuse_logins();
+    let notifier = Notifier::spawn(config(&server));
+    wait_until(|| notifier.status() != IrcStatus::Connecting).await;
+
+    assert_eq!(
+        notifier.status(),
+        IrcStatus::Refused("Bad authentication token.".to_string())
+    );
+    // longer than the first retry would take
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    assert_eq!(logins(&server), 1);
+}
+
+#[tokio::test]
+async fn quits_when_dropped() {
+    let server = MockIrcServer::start().unwrap();
+    let notifier = Notifier::spawn(config(&server));
+    wait_until(|| notifier.is_connected()).await;
+    drop(notifier);
+
+    wait_until(|| server.received().contains(&"QUIT".to_string())).await;
+}
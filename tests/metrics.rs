@@ -0,0 +1,117 @@
+use std::time::Duration;
+
+use osu_beatmap_watcher::osu::metrics::Metrics;
+use osu_beatmap_watcher::osu::types::RankStatus;
+use reqwest::StatusCode;
+
+/// The value of the series `name`, including its labels.
+fn value(rendered: &str, name: &str) -> Option<String> {
+    rendered
+        .lines()
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+        .map(str::to_string)
+}
+
+#[test]
+fn renders_empty_metrics() {
+    let rendered = Metrics::default().render();
+    assert!(rendered.contains("# TYPE osu_api_requests_total counter\n"));
+    assert_eq!(
+        value(&rendered, "osu_token_refreshes_total").as_deref(),
+        Some("0")
+    );
+    assert_eq!(value(&rendered, "osu_active_watches").as_deref(), Some("0"));
+    // no poll yet
+    assert!(!rendered.contains("osu_last_poll_timestamp_seconds"));
+}
+
+#[test]
+fn renders_requests_and_cumulative_latency() {
+    let metrics = Metrics::default();
+    metrics.record_request("beatmap", Some(StatusCode::OK), Duration::from_millis(200));
+    metrics.record_request("beatmap", None, Duration::from_secs(40));
+    let rendered = metrics.render();
+
+    for (series, expected) in [
+        (
+            r#"osu_api_requests_total{endpoint="beatmap",status="200"}"#,
+            "1",
+        ),
+        (
+            r#"osu_api_requests_total{endpoint="beatmap",status="error"}"#,
+            "1",
+        ),
+        (
+            r#"osu_api_request_duration_seconds_bucket{endpoint="beatmap",le="0.1"}"#,
+            "0",
+        ),
+        (
+            r#"osu_api_request_duration_seconds_bucket{endpoint="beatmap",le="0.25"}"#,
+            "1",
+        ),
+        (
+            r#"osu_api_request_duration_seconds_bucket{endpoint="beatmap",le="30"}"#,
+            "1",
+        ),
+        (
+            r#"osu_api_request_duration_seconds_bucket{endpoint="beatmap",le="+Inf"}"#,
+            "2",
+        ),
+        (
+            r#"osu_api_request_duration_seconds_count{endpoint="beatmap"}"#,
+            "2",
+        ),
+    ] {
+        assert_eq!(
+            value(&rendered, series).as_deref(),
+            Some(expected),
+            "{series}"
+        );
+    }
+}
+
+#[test]
+fn renders_watched_beatmaps_until_removed() {
+    let metrics = Metrics::default();
+    metrics.watch_started();
+    metrics.record_poll(75, RankStatus::Qualified, 1000);
+    let rendered = metrics.render();
+
+    assert_eq!(value(&rendered, "osu_active_watches").as_deref(), Some("1"));
+    assert_eq!(
+        value(
+            &rendered,
+            r#"osu_beatmap_status{beatmap_id="75",status="Qualified"}"#
+        )
+        .as_deref(),
+        Some("1")
+    );
+    assert_eq!(
+        value(
+            &rendered,
+            r#"osu_beatmap_status{beatmap_id="75",status="Ranked"}"#
+        )
+        .as_deref(),
+        Some("0")
+    );
+    assert_eq!(
+        value(
+            &rendered,
+            r#"osu_beatmap_last_poll_timestamp_seconds{beatmap_id="75"}"#
+        )
+        .as_deref(),
+        Some("1000")
+    );
+    assert_eq!(
+        value(&rendered, "osu_last_poll_timestamp_seconds").as_deref(),
+        Some("1000")
+    );
+
+    metrics.remove_beatmap(75);
+    metrics.watch_stopped();
+    // never below zero
+    metrics.watch_stopped();
+    let rendered = metrics.render();
+    assert!(!rendered.contains(r#"beatmap_id="75""#));
+    assert_eq!(value(&rendered, "osu_active_watches").as_deref(), Some("0"));
+}
@@ -0,0 +1,78 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use osu_beatmap_watcher::osu::rate_limit::{RateLimiter, MAX_BUDGET};
+use tokio::time::{self, Instant};
+
+/// Takes every token available right away.
+async fn drain(limiter: &RateLimiter) {
+    for _ in 0..limiter.stats().available {
+        limiter.acquire().await;
+    }
+    assert_eq!(limiter.stats().available, 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn refills_with_the_budget() {
+    let limiter = RateLimiter::new(60);
+    // ten seconds' worth at once
+    assert_eq!(limiter.stats().available, 10);
+    drain(&limiter).await;
+
+    time::advance(Duration::from_secs(3)).await;
+    assert_eq!(limiter.stats().available, 3);
+    time::advance(Duration::from_secs(60)).await;
+    assert_eq!(limiter.stats().available, 10);
+    assert_eq!(limiter.stats().recent, 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn serves_in_order() {
+    let limiter = Arc::new(RateLimiter::new(60));
+    drain(&limiter).await;
+
+    let served = Arc::new(Mutex::new(Vec::new()));
+    let mut tasks = Vec::new();
+    for index in 0..5 {
+        let limiter = limiter.clone();
+        let served = served.clone();
+        tasks.push(tokio::spawn(async move {
+            limiter.acquire().await;
+            served.lock().unwrap().push(index);
+        }));
+        // queued before the next one
+        tokio::task::yield_now().await;
+    }
+    assert_eq!(limiter.stats().queued, 5);
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    assert_eq!(*served.lock().unwrap(), [0, 1, 2, 3, 4]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn budget_change_applies_while_waiting() {
+    let limiter = Arc::new(RateLimiter::new(1));
+    drain(&limiter).await;
+
+    let start = Instant::now();
+    let waiting = tokio::spawn({
+        let limiter = limiter.clone();
+        async move { limiter.acquire().await }
+    });
+    time::advance(Duration::from_secs(1)).await;
+    limiter.set_budget(MAX_BUDGET);
+    waiting.await.unwrap();
+
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn clamps_the_budget() {
+    let limiter = RateLimiter::new(0);
+    assert_eq!(limiter.stats().budget, 1);
+    assert_eq!(limiter.stats().available, 1);
+    limiter.set_budget(1000);
+    assert_eq!(limiter.stats().budget, MAX_BUDGET);
+}
@@ -0,0 +1,218 @@
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use osu_beatmap_watcher::osu::api::Backoff;
+use osu_beatmap_watcher::osu::mock::{self, MockServer};
+use osu_beatmap_watcher::osu::types::{Beatmap, BeatmapAttributes, NominationsSummary, RankStatus};
+use osu_beatmap_watcher::osu::watcher::Transition;
+use osu_beatmap_watcher::osu::{OsuApi, WatchEvent, Watcher};
+use serde_json::json;
+
+async fn log_in(server: &MockServer) -> OsuApi {
+    OsuApi::new(server.url())
+        .log_in("id", "secret")
+        .await
+        .unwrap()
+}
+
+/// The next event, failing instead of waiting forever.
+async fn next(watcher: &mut Watcher) -> Option<WatchEvent> {
+    tokio::time::timeout(Duration::from_secs(10), watcher.next())
+        .await
+        .unwrap()
+}
+
+async fn next_beatmap(watcher: &mut Watcher) -> Beatmap {
+    match next(watcher).await {
+        Some(WatchEvent::Beatmap(beatmap)) => beatmap,
+        event => panic!("expected a beatmap, got {event:?}"),
+    }
+}
+
+fn nominated(mut beatmap: Beatmap, current: u32) -> Beatmap {
+    let nominations: NominationsSummary =
+        serde_json::from_value(json!({ "current": current, "required": 2 })).unwrap();
+    beatmap.beatmapset.nominations_summary = Some(nominations);
+    beatmap
+}
+
+#[tokio::test]
+async fn reports_status_changes_until_a_stop_status() {
+    let server = MockServer::start().unwrap();
+    server.set_beatmap(mock::beatmap(75, 1, RankStatus::Pending));
+    let api = log_in(&server).await;
+    let mut watcher = api.watch(75, vec![RankStatus::Ranked], None);
+
+    assert_eq!(next_beatmap(&mut watcher).await.ranked, RankStatus::Pending);
+    server.set_status(75, RankStatus::Qualified);
+    assert!(matches!(
+        next(&mut watcher).await,
+        Some(WatchEvent::Transition(Transition::StatusChanged {
+            from: RankStatus::Pending,
+            to: RankStatus::Qualified,
+        }))
+    ));
+    assert_eq!(
+        next_beatmap(&mut watcher).await.ranked,
+        RankStatus::Qualified
+    );
+
+    server.set_status(75, RankStatus::Ranked);
+    assert!(matches!(
+        next(&mut watcher).await,
+        Some(WatchEvent::Transition(Transition::StatusChanged {
+            from: RankStatus::Qualified,
+            to: RankStatus::Ranked,
+        }))
+    ));
+    assert_eq!(next_beatmap(&mut watcher).await.ranked, RankStatus::Ranked);
+    assert!(next(&mut watcher).await.is_none());
+}
+
+#[tokio::test]
+async fn reports_nominations() {
+    let server = MockServer::start().unwrap();
+    let beatmap = mock::beatmap(75, 1, RankStatus::Pending);
+    server.set_beatmap(nominated(beatmap.clone(), 1));
+    let api = log_in(&server).await;
+    let mut watcher = api.watch(75, vec![RankStatus::Ranked], None);
+
+    let polled = next_beatmap(&mut watcher).await;
+    assert_eq!(polled.beatmapset.nominations_summary.unwrap().current, 1);
+    server.set_beatmap(nominated(beatmap.clone(), 2));
+    match next(&mut watcher).await {
+        Some(WatchEvent::Transition(Transition::NominationAdded(nominations))) => {
+            assert_eq!(nominations.current, 2);
+            assert_eq!(nominations.required(), Some(2));
+        }
+        event => panic!("expected a nomination, got {event:?}"),
+    }
+    next_beatmap(&mut watcher).await;
+
+    server.set_beatmap(nominated(beatmap, 0));
+    assert!(matches!(
+        next(&mut watcher).await,
+        Some(WatchEvent::Transition(Transition::NominationRemoved(_)))
+    ));
+}
+
+#[tokio::test]
+async fn reports_changes_since_the_previous_beatmap_first() {
+    let server = MockServer::start().unwrap();
+    let previous = mock::beatmap(75, 1, RankStatus::Pending);
+    server.set_beatmap(mock::beatmap(75, 1, RankStatus::Ranked));
+    let api = log_in(&server).await;
+    let mut watcher = api.watch(75, vec![RankStatus::Ranked], Some(previous));
+
+    assert!(matches!(
+        next(&mut watcher).await,
+        Some(WatchEvent::Transition(Transition::StatusChanged {
+            from: RankStatus::Pending,
+            to: RankStatus::Ranked,
+        }))
+    ));
+    assert_eq!(next_beatmap(&mut watcher).await.ranked, RankStatus::Ranked);
+    assert!(next(&mut watcher).await.is_none());
+}
+
+#[tokio::test]
+async fn reports_updates_with_their_changes() {
+    let server = MockServer::start().unwrap();
+    let mut previous = mock::beatmap(75, 1, RankStatus::Pending);
+    previous.checksum = Some("a".to_string());
+    let mut updated = previous.clone();
+    updated.checksum = Some("b".to_string());
+    updated.ar = 9.3;
+    server.set_beatmap(updated);
+    let api = log_in(&server).await;
+    let mut watcher = api.watch(75, vec![RankStatus::Ranked], Some(previous));
+
+    match next(&mut watcher).await {
+        Some(WatchEvent::Transition(transition @ Transition::Updated { .. })) => {
+            assert_eq!(transition.to_string(), "Updated: AR 9 → 9.3");
+        }
+        event => panic!("expected an update, got {event:?}"),
+    }
+    next_beatmap(&mut watcher).await;
+}
+
+#[tokio::test]
+async fn reports_no_update_without_a_previous_checksum() {
+    let server = MockServer::start().unwrap();
+    // as restored from the history
+    let mut previous = mock::beatmap(75, 1, RankStatus::Pending);
+    previous.last_updated = None;
+    let mut current = previous.clone();
+    current.checksum = Some("b".to_string());
+    current.last_updated = Some("2023-01-01T00:00:00Z".to_string());
+    server.set_beatmap(current);
+    let api = log_in(&server).await;
+    let mut watcher = api.watch(75, vec![RankStatus::Ranked], Some(previous));
+
+    assert_eq!(next_beatmap(&mut watcher).await.ranked, RankStatus::Pending);
+}
+
+#[test]
+fn lists_changed_attributes() {
+    let old = mock::beatmap(75, 1, RankStatus::Pending).attributes();
+    assert!(old.changes(&old).is_empty());
+
+    let new = BeatmapAttributes {
+        // rounds to the same
+        stars: old.stars + 0.001,
+        ..old
+    };
+    assert!(old.changes(&new).is_empty());
+
+    let new = BeatmapAttributes {
+        stars: 5.5,
+        od: 8.5,
+        length: 185,
+        spinners: 2,
+        ..old
+    };
+    assert_eq!(
+        old.changes(&new),
+        [
+            "★ 5.00 → 5.50",
+            "OD 8 → 8.5",
+            "Length 3:00 → 3:05",
+            "Spinners 1 → 2"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn ends_when_the_beatmap_does_not_exist() {
+    let server = MockServer::start().unwrap();
+    let api = log_in(&server).await;
+    let mut watcher = api.watch(75, vec![RankStatus::Ranked], None);
+
+    assert!(matches!(
+        next(&mut watcher).await,
+        Some(WatchEvent::NotFound)
+    ));
+    assert!(next(&mut watcher).await.is_none());
+}
+
+#[tokio::test]
+async fn retries_while_offline() {
+    let server = MockServer::start().unwrap();
+    let api = log_in(&server).await;
+    drop(server);
+
+    let mut watcher = api.watch(75, vec![RankStatus::Ranked], None);
+    assert!(matches!(
+        next(&mut watcher).await,
+        Some(WatchEvent::Offline { retry_in }) if retry_in == Duration::from_secs(2)
+    ));
+}
+
+#[test]
+fn backoff_doubles_up_to_a_minute() {
+    let mut backoff = Backoff::new();
+    let delays = (0..8)
+        .map(|_| backoff.next_delay().as_secs())
+        .collect::<Vec<_>>();
+    assert_eq!(delays, [2, 4, 8, 16, 32, 60, 60, 60]);
+}